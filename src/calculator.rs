@@ -1,16 +1,35 @@
-use anyhow::{anyhow, Result};
+use anyhow::Result;
 use serde::Serialize;
+use std::fmt::{Display, Formatter};
 
+use crate::explain::Trace;
+use crate::model::RequestUnitModel;
 use crate::source::WorkloadDescription;
 
 const KILO: u64 = 1024;
 const MEGA: u64 = KILO * 1024;
 const HOURS_PER_MONTH: u64 = 730;
 
+/// Request units billed for every KiB of network egress. A million request
+/// units of the usage is binary, like its MiB, so a GiB of egress is billed
+/// as one million request units.
+pub const EGRESS_REQUEST_UNITS_PER_KIB: f64 = 1f64;
+
+/// Version of the regional price list below, recorded in every report so
+/// estimations made with different prices can be told apart.
+pub const PRICING_VERSION: &str = "2024-06";
+
+#[derive(Default, Debug, Clone, Serialize)]
 pub struct WorkloadUsage {
-    row_based_storage_in_mib: u64,
-    network_egress_in_mib: u64,
-    request_units_in_million: u64,
+    pub row_based_storage_in_mib: u64,
+    pub network_egress_in_mib: u64,
+    pub request_units_in_million: u64,
+}
+
+#[derive(Default, Debug, Clone, Copy, Serialize)]
+pub struct CostRange {
+    pub low: f64,
+    pub high: f64,
 }
 
 #[derive(Default, Debug, Serialize)]
@@ -18,58 +37,256 @@ pub struct WorkloadEstimation {
     pub storage_cost: f64,
     pub request_units_cost: f64,
     pub free_credit: f64,
+    pub storage_cost_range: CostRange,
+    pub request_units_cost_range: CostRange,
+    pub usage: WorkloadUsage,
+    #[serde(skip)]
+    pub trace: Trace,
 }
 
-fn calculate(
+impl WorkloadEstimation {
+    fn total(&self, storage_cost: f64, request_units_cost: f64) -> f64 {
+        (storage_cost + request_units_cost - self.free_credit).max(0f64)
+    }
+
+    pub fn total_cost(&self) -> f64 {
+        self.total(self.storage_cost, self.request_units_cost)
+    }
+
+    pub fn total_cost_range(&self) -> CostRange {
+        CostRange {
+            low: self.total(
+                self.storage_cost_range.low,
+                self.request_units_cost_range.low,
+            ),
+            high: self.total(
+                self.storage_cost_range.high,
+                self.request_units_cost_range.high,
+            ),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct InvalidRegion(String);
+
+impl Display for InvalidRegion {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "The region '{}' is invalid", self.0)
+    }
+}
+
+impl std::error::Error for InvalidRegion {}
+
+struct Pricing {
     row_based_price: f64,
     ru_price: f64,
     free_credit: f64,
-    usages: Vec<WorkloadUsage>,
-) -> Vec<WorkloadEstimation> {
-    usages
-        .into_iter()
-        .map(|usage| WorkloadEstimation {
-            storage_cost: usage.row_based_storage_in_mib as f64 * row_based_price / 1024f64,
-            request_units_cost: ((usage.network_egress_in_mib as f64 / 1024f64)
-                + usage.request_units_in_million as f64)
-                * ru_price,
-            free_credit,
-        })
-        .collect()
 }
 
-fn estimate_usages(workloads: &[WorkloadDescription]) -> Vec<WorkloadUsage> {
-    workloads
-        .iter()
-        .map(|workload| {
-            let read_request_units_per_hour = (workload.read.requests_per_hour.unwrap_or(0) / 8)
-                + (workload.read.bytes_per_hour / (64 * KILO));
-            let write_request_units_per_hour = (workload.write.requests_per_hour.unwrap_or(0)
-                + (workload.write.bytes_per_hour / KILO))
-                * 3;
-            let request_units_per_hour = read_request_units_per_hour + write_request_units_per_hour;
-            WorkloadUsage {
-                row_based_storage_in_mib: (workload.storage.data_in_bytes
-                    + workload.storage.index_in_bytes)
-                    / MEGA,
-                network_egress_in_mib: workload.egress.bytes_per_hour * HOURS_PER_MONTH / MEGA,
-                request_units_in_million: request_units_per_hour * HOURS_PER_MONTH / MEGA,
-            }
-        })
-        .collect()
+impl Pricing {
+    fn storage_cost(&self, usage: &WorkloadUsage) -> f64 {
+        usage.row_based_storage_in_mib as f64 * self.row_based_price / 1024f64
+    }
+
+    fn request_units_cost(&self, usage: &WorkloadUsage) -> f64 {
+        (usage.network_egress_in_mib as f64 * KILO as f64 * EGRESS_REQUEST_UNITS_PER_KIB
+            / MEGA as f64
+            + usage.request_units_in_million as f64)
+            * self.ru_price
+    }
+
+    fn calculate(
+        &self,
+        model: &RequestUnitModel,
+        workload: &WorkloadDescription,
+    ) -> WorkloadEstimation {
+        let (low, high) = workload
+            .bounds
+            .as_ref()
+            .map_or((workload, workload), |bounds| (&bounds.low, &bounds.high));
+        let mut trace = Trace::new("estimation");
+        let (usage, low, high) = (
+            estimate_usage(model, workload, &mut trace),
+            estimate_usage(model, low, &mut Trace::default()),
+            estimate_usage(model, high, &mut Trace::default()),
+        );
+        WorkloadEstimation {
+            storage_cost: trace.step(
+                "storage_cost",
+                "row_based_storage_in_mib * row_based_price / 1024",
+                &[
+                    (
+                        "row_based_storage_in_mib",
+                        usage.row_based_storage_in_mib.into(),
+                    ),
+                    ("row_based_price", self.row_based_price.into()),
+                ],
+                self.storage_cost(&usage),
+            ),
+            request_units_cost: trace.step(
+                "request_units_cost",
+                "(network_egress_in_mib / 1024 + request_units_in_million) * ru_price",
+                &[
+                    ("network_egress_in_mib", usage.network_egress_in_mib.into()),
+                    (
+                        "request_units_in_million",
+                        usage.request_units_in_million.into(),
+                    ),
+                    ("ru_price", self.ru_price.into()),
+                ],
+                self.request_units_cost(&usage),
+            ),
+            free_credit: self.free_credit,
+            storage_cost_range: CostRange {
+                low: self.storage_cost(&low),
+                high: self.storage_cost(&high),
+            },
+            request_units_cost_range: CostRange {
+                low: self.request_units_cost(&low),
+                high: self.request_units_cost(&high),
+            },
+            usage,
+            trace,
+        }
+    }
+}
+
+fn estimate_request_units_per_hour(
+    model: &RequestUnitModel,
+    workload: &WorkloadDescription,
+    trace: &mut Trace,
+) -> u64 {
+    if let Some(request_units_per_hour) = workload.request_units_per_hour {
+        return trace.step(
+            "request_units_per_hour",
+            "measured by the statement summary",
+            &[],
+            request_units_per_hour,
+        );
+    }
+    let read_requests_per_hour = workload.read.requests_per_hour.unwrap_or(0);
+    let write_requests_per_hour = workload.write.requests_per_hour.unwrap_or(0);
+    let read_request_units_per_hour = trace.step(
+        "read_request_units_per_hour",
+        "read_requests_per_hour * read_request_units_per_request + read_bytes_per_hour / 1KiB * read_request_units_per_kib",
+        &[
+            ("read_requests_per_hour", read_requests_per_hour.into()),
+            ("read_request_units_per_request", model.read_request_units_per_request.into()),
+            ("read_bytes_per_hour", workload.read.bytes_per_hour.into()),
+            ("read_request_units_per_kib", model.read_request_units_per_kib.into()),
+        ],
+        model.read_request_units(
+            read_requests_per_hour as f64,
+            workload.read.bytes_per_hour as f64,
+        ) as u64,
+    );
+    let write_request_units_per_hour = trace.step(
+        "write_request_units_per_hour",
+        "write_requests_per_hour * write_request_units_per_request + write_bytes_per_hour / 1KiB * write_request_units_per_kib",
+        &[
+            ("write_requests_per_hour", write_requests_per_hour.into()),
+            ("write_request_units_per_request", model.write_request_units_per_request.into()),
+            ("write_bytes_per_hour", workload.write.bytes_per_hour.into()),
+            ("write_request_units_per_kib", model.write_request_units_per_kib.into()),
+        ],
+        model.write_request_units(
+            write_requests_per_hour as f64,
+            workload.write.bytes_per_hour as f64,
+        ) as u64,
+    );
+    trace.step(
+        "request_units_per_hour",
+        "read_request_units_per_hour + write_request_units_per_hour",
+        &[
+            (
+                "read_request_units_per_hour",
+                read_request_units_per_hour.into(),
+            ),
+            (
+                "write_request_units_per_hour",
+                write_request_units_per_hour.into(),
+            ),
+        ],
+        read_request_units_per_hour + write_request_units_per_hour,
+    )
+}
+
+fn estimate_usage(
+    model: &RequestUnitModel,
+    workload: &WorkloadDescription,
+    trace: &mut Trace,
+) -> WorkloadUsage {
+    let request_units_per_hour = estimate_request_units_per_hour(model, workload, trace);
+    let storage_in_bytes = workload.storage.data_in_bytes + workload.storage.index_in_bytes;
+    WorkloadUsage {
+        row_based_storage_in_mib: trace.step(
+            "row_based_storage_in_mib",
+            "(data_in_bytes + index_in_bytes) * storage_factor / 1MiB",
+            &[
+                ("data_in_bytes", workload.storage.data_in_bytes.into()),
+                ("index_in_bytes", workload.storage.index_in_bytes.into()),
+                ("storage_factor", model.storage_factor.into()),
+            ],
+            (storage_in_bytes as f64 * model.storage_factor) as u64 / MEGA,
+        ),
+        network_egress_in_mib: trace.step(
+            "network_egress_in_mib",
+            "egress_bytes_per_hour * 730 / 1MiB",
+            &[(
+                "egress_bytes_per_hour",
+                workload.egress.bytes_per_hour.into(),
+            )],
+            workload.egress.bytes_per_hour * HOURS_PER_MONTH / MEGA,
+        ),
+        request_units_in_million: trace.step(
+            "request_units_in_million",
+            "request_units_per_hour * 730 / 1Mi",
+            &[("request_units_per_hour", request_units_per_hour.into())],
+            request_units_per_hour * HOURS_PER_MONTH / MEGA,
+        ),
+    }
+}
+
+/// The row-based storage price per GiB, the request unit price per million and
+/// the free credit of every region.
+const PRICES: [(&str, f64, f64, f64); 5] = [
+    ("us-east-1", 0.2, 0.1, 6.0),
+    ("us-west-2", 0.2, 0.1, 6.0),
+    ("eu-central-1", 0.24, 0.12, 7.2),
+    ("ap-southeast-1", 0.24, 0.12, 7.2),
+    ("ap-northeast-1", 0.24, 0.12, 7.2),
+];
+
+pub fn regions() -> impl Iterator<Item = &'static str> {
+    PRICES.iter().map(|price| price.0)
+}
+
+fn pricing(region: &str) -> Result<Pricing> {
+    let Some(&(_, row_based_price, ru_price, free_credit)) =
+        PRICES.iter().find(|price| price.0 == region)
+    else {
+        return Err(InvalidRegion(region.into()).into());
+    };
+    Ok(Pricing {
+        row_based_price,
+        ru_price,
+        free_credit,
+    })
+}
+
+pub fn check_region(region: &str) -> Result<()> {
+    pricing(region).map(|_| ())
 }
 
 pub fn estimate(
     region: &str,
+    model: &RequestUnitModel,
     workloads: &[WorkloadDescription],
 ) -> Result<Vec<WorkloadEstimation>> {
-    let usages = estimate_usages(workloads);
-    match region {
-        "us-east-1" => Ok(calculate(0.2, 0.1, 6.0, usages)),
-        "us-west-2" => Ok(calculate(0.2, 0.1, 6.0, usages)),
-        "eu-central-1" => Ok(calculate(0.24, 0.12, 7.2, usages)),
-        "ap-southeast-1" => Ok(calculate(0.24, 0.12, 7.2, usages)),
-        "ap-northeast-1" => Ok(calculate(0.24, 0.12, 7.2, usages)),
-        _ => Err(anyhow!("The region '{}' is invalid", region)),
-    }
+    let pricing = pricing(region)?;
+    Ok(workloads
+        .iter()
+        .map(|workload| pricing.calculate(model, workload))
+        .collect())
 }
@@ -0,0 +1,473 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use colored::Colorize;
+use prettytable::{row, Table};
+use readable::num::Float;
+
+use crate::calculator::EGRESS_REQUEST_UNITS_PER_KIB;
+use crate::config;
+use crate::model::RequestUnitModel;
+use crate::report::{Report, WorkloadInput};
+use crate::source::Diagnostics;
+
+const HOURS_PER_MONTH: f64 = 730f64;
+const KIB: f64 = 1024f64;
+const GIB: f64 = 1024f64 * 1024f64 * 1024f64;
+const FACTORS: usize = 4;
+const FACTOR_NAMES: [&str; FACTORS] = [
+    "read_request_units_per_request",
+    "read_request_units_per_kib",
+    "write_request_units_per_request",
+    "write_request_units_per_kib",
+];
+
+#[derive(Debug, Default)]
+struct BilledUsage {
+    request_units: f64,
+    storage_in_gib: f64,
+    rows: usize,
+}
+
+/// A source-side workload paired with what TiDB Serverless actually billed for it.
+struct Sample {
+    name: String,
+    /// Monthly request units contributed by each factor of the base model.
+    request_units: [f64; FACTORS],
+    egress_request_units: f64,
+    storage_in_gib: f64,
+    billed: BilledUsage,
+}
+
+fn load_snapshots(files: &[String]) -> Result<Vec<(String, WorkloadInput)>> {
+    let mut workloads = Vec::new();
+    for file in files {
+        let snapshot: Report = config::load(file, "workload snapshot")?;
+        workloads.extend(
+            snapshot
+                .clusters
+                .into_iter()
+                .map(|cluster| (cluster.name, cluster.workload)),
+        );
+    }
+    Ok(workloads)
+}
+
+fn normalize_header(header: &str) -> String {
+    let header = header.split('(').next().unwrap_or(header);
+    header
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .collect::<String>()
+        .to_lowercase()
+}
+
+fn find_column(headers: &[String], aliases: &[&str]) -> Result<usize> {
+    headers
+        .iter()
+        .position(|header| aliases.contains(&header.as_str()))
+        .ok_or_else(|| {
+            anyhow!(
+                "The bill has no '{}' column. Expected one of: {}",
+                aliases[0],
+                aliases.join(", ")
+            )
+        })
+}
+
+fn parse_number(value: &str) -> Result<f64> {
+    let value: String = value
+        .chars()
+        .filter(|c| !matches!(c, ',' | '$' | ' '))
+        .collect();
+    if value.is_empty() {
+        return Ok(0f64);
+    }
+    value
+        .parse()
+        .map_err(|_| anyhow!("The value '{}' in the bill is not a number", value))
+}
+
+/// Reads a CSV bill export with one or more rows per cluster. Request units of
+/// all rows of a cluster are summed, the row-based storage in GiB is averaged.
+fn load_bill(file: &str) -> Result<HashMap<String, BilledUsage>> {
+    let mut reader = csv::Reader::from_path(file)?;
+    let headers: Vec<String> = reader.headers()?.iter().map(normalize_header).collect();
+    let cluster = find_column(&headers, &["cluster", "clustername", "clusterid"])?;
+    let request_units = find_column(
+        &headers,
+        &["requestunits", "requestunit", "ru", "rus", "rru"],
+    )?;
+    let storage = find_column(
+        &headers,
+        &[
+            "rowbasedstorage",
+            "rowbasedstoragegib",
+            "storage",
+            "storagegib",
+        ],
+    )?;
+    let mut bill: HashMap<String, BilledUsage> = HashMap::new();
+    for record in reader.records() {
+        let record = record?;
+        let usage = bill
+            .entry(record.get(cluster).unwrap_or_default().trim().to_string())
+            .or_default();
+        usage.request_units += parse_number(record.get(request_units).unwrap_or_default())?;
+        usage.storage_in_gib += parse_number(record.get(storage).unwrap_or_default())?;
+        usage.rows += 1;
+    }
+    for usage in bill.values_mut() {
+        usage.storage_in_gib /= usage.rows as f64;
+    }
+    Ok(bill)
+}
+
+fn coefficients(model: &RequestUnitModel) -> [f64; FACTORS] {
+    [
+        model.read_request_units_per_request,
+        model.read_request_units_per_kib,
+        model.write_request_units_per_request,
+        model.write_request_units_per_kib,
+    ]
+}
+
+fn sample(
+    model: &RequestUnitModel,
+    name: &str,
+    workload: &WorkloadInput,
+    billed: BilledUsage,
+) -> Sample {
+    let volumes = [
+        workload.read_requests_per_hour as f64,
+        workload.read_bytes_per_hour as f64 / KIB,
+        workload.write_requests_per_hour as f64,
+        workload.write_bytes_per_hour as f64 / KIB,
+    ];
+    let coefficients = coefficients(model);
+    Sample {
+        name: name.to_string(),
+        request_units: std::array::from_fn(|i| volumes[i] * coefficients[i] * HOURS_PER_MONTH),
+        egress_request_units: workload.egress_bytes_per_hour as f64 / KIB
+            * EGRESS_REQUEST_UNITS_PER_KIB
+            * HOURS_PER_MONTH,
+        storage_in_gib: (workload.storage_data_in_bytes + workload.storage_index_in_bytes) as f64
+            * model.storage_factor
+            / GIB,
+        billed,
+    }
+}
+
+/// Solves the linear system with gaussian elimination and partial pivoting.
+fn solve(mut a: [[f64; FACTORS]; FACTORS], mut b: [f64; FACTORS]) -> Result<[f64; FACTORS]> {
+    for column in 0..FACTORS {
+        let pivot = (column..FACTORS)
+            .max_by(|x, y| a[*x][column].abs().total_cmp(&a[*y][column].abs()))
+            .unwrap_or(column);
+        if a[pivot][column] == 0f64 {
+            return Err(anyhow!("The calibration samples are degenerate"));
+        }
+        a.swap(column, pivot);
+        b.swap(column, pivot);
+        let (upper, lower) = a.split_at_mut(column + 1);
+        let pivot_row = &upper[column];
+        for (offset, row) in lower.iter_mut().enumerate() {
+            let factor = row[column] / pivot_row[column];
+            for (value, pivot_value) in row.iter_mut().zip(pivot_row).skip(column) {
+                *value -= factor * pivot_value;
+            }
+            b[column + 1 + offset] -= factor * b[column];
+        }
+    }
+    let mut x = [0f64; FACTORS];
+    for row in (0..FACTORS).rev() {
+        let sum: f64 = (row + 1..FACTORS).map(|k| a[row][k] * x[k]).sum();
+        x[row] = (b[row] - sum) / a[row][row];
+    }
+    Ok(x)
+}
+
+/// Fits a scale for every factor of the base model by least squares, with the
+/// factors marked as fixed pinned to zero. A small ridge term pulling the
+/// other scales towards one keeps the fit stable when there are fewer samples
+/// than factors or a factor is absent from every sample.
+fn fit_scales_with(samples: &[Sample], fixed: &[bool; FACTORS]) -> Result<[f64; FACTORS]> {
+    let mut a = [[0f64; FACTORS]; FACTORS];
+    let mut b = [0f64; FACTORS];
+    for sample in samples {
+        let target = sample.billed.request_units - sample.egress_request_units;
+        for (i, row) in a.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value += sample.request_units[i] * sample.request_units[j];
+            }
+            b[i] += sample.request_units[i] * target;
+        }
+    }
+    let trace: f64 = (0..FACTORS).map(|i| a[i][i]).sum();
+    let ridge = f64::max(trace * 1e-9, 1e-6);
+    for i in 0..FACTORS {
+        a[i][i] += ridge;
+        b[i] += ridge;
+    }
+    for i in (0..FACTORS).filter(|i| fixed[*i]) {
+        a[i] = [0f64; FACTORS];
+        for row in a.iter_mut() {
+            row[i] = 0f64;
+        }
+        a[i][i] = 1f64;
+        b[i] = 0f64;
+    }
+    solve(a, b)
+}
+
+/// Fits the scales without letting any of them go negative: the most negative
+/// scale is pinned to zero and the others are fitted again until none is left.
+/// Returns the scales along with the factors pinned to zero.
+fn fit_scales(samples: &[Sample]) -> Result<([f64; FACTORS], [bool; FACTORS])> {
+    let mut fixed = [false; FACTORS];
+    loop {
+        let scales = fit_scales_with(samples, &fixed)?;
+        let negative = (0..FACTORS)
+            .filter(|i| !fixed[*i] && scales[*i] < 0f64)
+            .min_by(|x, y| scales[*x].total_cmp(&scales[*y]));
+        match negative {
+            Some(i) => fixed[i] = true,
+            None => return Ok((scales, fixed)),
+        }
+    }
+}
+
+fn fit_storage_factor(model: &RequestUnitModel, samples: &[Sample]) -> f64 {
+    let estimated: f64 = samples.iter().map(|sample| sample.storage_in_gib).sum();
+    let billed: f64 = samples
+        .iter()
+        .map(|sample| sample.billed.storage_in_gib)
+        .sum();
+    if estimated > 0f64 && billed > 0f64 {
+        model.storage_factor * billed / estimated
+    } else {
+        model.storage_factor
+    }
+}
+
+fn estimated_request_units(sample: &Sample, scales: &[f64; FACTORS]) -> f64 {
+    sample.egress_request_units
+        + (0..FACTORS)
+            .map(|i| sample.request_units[i] * scales[i])
+            .sum::<f64>()
+}
+
+pub fn calibrate(
+    diagnostics: &dyn Diagnostics,
+    base: RequestUnitModel,
+    snapshots: &[String],
+    bill: &str,
+    name: Option<String>,
+    version: Option<String>,
+) -> Result<RequestUnitModel> {
+    let mut bill = load_bill(bill)?;
+    let samples: Vec<Sample> = load_snapshots(snapshots)?
+        .iter()
+        .filter_map(|(name, workload)| {
+            bill.remove(name)
+                .map(|billed| sample(&base, name, workload, billed))
+        })
+        .collect();
+    if samples.is_empty() {
+        return Err(anyhow!(
+            "None of the clusters in the snapshots can be found in the bill"
+        ));
+    }
+
+    let (scales, fixed) = fit_scales(&samples)?;
+    for (factor, _) in FACTOR_NAMES.iter().zip(fixed).filter(|(_, fixed)| *fixed) {
+        diagnostics.warn("calibration_factor_fixed", &format!("The best fit gives a negative {}, which is fixed at zero and the other factors fitted again. Check that the bill and the snapshots cover the same clusters and period.", factor));
+    }
+    let coefficients = coefficients(&base);
+    let calibrated: [f64; FACTORS] = std::array::from_fn(|i| coefficients[i] * scales[i]);
+    let model = RequestUnitModel {
+        name: name.unwrap_or_else(|| "calibrated".into()),
+        version: version.unwrap_or_else(|| Utc::now().format("%Y-%m-%d").to_string()),
+        read_request_units_per_request: calibrated[0],
+        read_request_units_per_kib: calibrated[1],
+        write_request_units_per_request: calibrated[2],
+        write_request_units_per_kib: calibrated[3],
+        storage_factor: fit_storage_factor(&base, &samples),
+        ..base.clone()
+    };
+    report(&base, &model, &samples, &scales);
+    Ok(model)
+}
+
+fn report(
+    base: &RequestUnitModel,
+    model: &RequestUnitModel,
+    samples: &[Sample],
+    scales: &[f64; FACTORS],
+) {
+    println!(
+        "Calibrated the request unit model with {} cluster(s)",
+        format!("{}", samples.len()).bold().green()
+    );
+    let mut table = Table::new();
+    table.set_titles(row![bFg -> "Factor", bFgr -> base.name.clone(), bFgr -> model.name.clone()]);
+    let factors = FACTOR_NAMES.iter().chain(&["storage_factor"]);
+    let before = coefficients(base).into_iter().chain([base.storage_factor]);
+    let after = coefficients(model)
+        .into_iter()
+        .chain([model.storage_factor]);
+    for ((factor, before), after) in factors.zip(before).zip(after) {
+        table.add_row(
+            row![bFg -> factor, bFgr -> format!("{:.6}", before), bFgr -> format!("{:.6}", after)],
+        );
+    }
+    table.printstd();
+
+    let mut table = Table::new();
+    table.set_titles(
+        row![bFg -> "Cluster", bFgr -> "Billed RU", bFgr -> "Base RU", bFgr -> "Calibrated RU"],
+    );
+    for sample in samples {
+        table.add_row(row![bFg -> sample.name,
+            bFgr -> Float::from_0(sample.billed.request_units),
+            bFgr -> Float::from_0(estimated_request_units(sample, &[1f64; FACTORS])),
+            bFgr -> Float::from_0(estimated_request_units(sample, scales))]);
+    }
+    table.printstd();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn synthetic_sample(request_units: [f64; FACTORS], scales: &[f64; FACTORS]) -> Sample {
+        let egress_request_units = 1000f64;
+        Sample {
+            name: "synthetic".into(),
+            request_units,
+            egress_request_units,
+            storage_in_gib: 0f64,
+            billed: BilledUsage {
+                request_units: egress_request_units
+                    + (0..FACTORS)
+                        .map(|i| request_units[i] * scales[i])
+                        .sum::<f64>(),
+                storage_in_gib: 0f64,
+                rows: 1,
+            },
+        }
+    }
+
+    fn synthetic_samples(scales: &[f64; FACTORS]) -> Vec<Sample> {
+        [
+            [4e6, 1e6, 2e5, 3e5],
+            [1e6, 3e6, 5e5, 1e5],
+            [2e6, 2e6, 1e6, 4e5],
+            [5e5, 1e6, 3e5, 9e5],
+            [3e6, 5e5, 7e5, 2e5],
+            [1e6, 1e6, 1e5, 6e5],
+        ]
+        .into_iter()
+        .map(|request_units| synthetic_sample(request_units, scales))
+        .collect()
+    }
+
+    #[test]
+    fn solve_known_system() {
+        let a = [
+            [2f64, 1f64, 0f64, 0f64],
+            [1f64, 3f64, 1f64, 0f64],
+            [0f64, 1f64, 4f64, 1f64],
+            [0f64, 0f64, 1f64, 5f64],
+        ];
+        let expected = [1f64, -2f64, 3f64, 0.5f64];
+        let b: [f64; FACTORS] =
+            std::array::from_fn(|i| (0..FACTORS).map(|j| a[i][j] * expected[j]).sum());
+        let x = solve(a, b).unwrap();
+        for (x, expected) in x.iter().zip(expected) {
+            assert!((x - expected).abs() < 1e-9, "{} != {}", x, expected);
+        }
+    }
+
+    #[test]
+    fn solve_degenerate_system() {
+        let a = [
+            [1f64, 2f64, 0f64, 0f64],
+            [2f64, 4f64, 0f64, 0f64],
+            [0f64, 0f64, 1f64, 0f64],
+            [0f64, 0f64, 0f64, 1f64],
+        ];
+        assert!(solve(a, [1f64; FACTORS]).is_err());
+    }
+
+    #[test]
+    fn fit_scales_recovers_known_scales() {
+        let scales = [0.5f64, 2f64, 1.5f64, 0.8f64];
+        let (fitted, fixed) = fit_scales(&synthetic_samples(&scales)).unwrap();
+        assert_eq!(fixed, [false; FACTORS]);
+        for (fitted, scale) in fitted.iter().zip(scales) {
+            assert!((fitted - scale).abs() < 1e-3, "{} != {}", fitted, scale);
+        }
+    }
+
+    #[test]
+    fn fit_scales_fixes_negative_factors_at_zero() {
+        let (fitted, fixed) = fit_scales(&synthetic_samples(&[1f64, 1f64, -0.5f64, 1f64])).unwrap();
+        assert_eq!(fixed, [false, false, true, false]);
+        assert_eq!(fitted[2], 0f64);
+        assert!(fitted.iter().all(|scale| *scale >= 0f64));
+    }
+
+    fn parse_bill(name: &str, content: &str) -> HashMap<String, BilledUsage> {
+        let file = std::env::temp_dir().join(format!(
+            "serverless-cost-calculator-{}-{}.csv",
+            std::process::id(),
+            name
+        ));
+        std::fs::write(&file, content).unwrap();
+        let bill = load_bill(file.to_str().unwrap());
+        std::fs::remove_file(&file).unwrap();
+        bill.unwrap()
+    }
+
+    #[test]
+    fn load_bill_header_aliases() {
+        let clusters = ["Cluster", "Cluster Name", "Cluster ID"];
+        let request_units = ["Request Units", "Request Unit", "RU", "RUs", "RRU"];
+        let storages = [
+            "Row-based Storage",
+            "Row-based Storage (GiB)",
+            "Storage",
+            "Storage GiB",
+        ];
+        for (index, request_unit) in request_units.iter().enumerate() {
+            let cluster = clusters[index % clusters.len()];
+            let storage = storages[index % storages.len()];
+            let bill = parse_bill(
+                &index.to_string(),
+                &format!(
+                    "{},{},{}\norders,\"1,000\",10\norders,500,20\nusers,$250,4\n",
+                    cluster, request_unit, storage
+                ),
+            );
+            let orders = &bill["orders"];
+            assert_eq!(orders.request_units, 1500f64);
+            assert_eq!(orders.storage_in_gib, 15f64);
+            assert_eq!(orders.rows, 2);
+            assert_eq!(bill["users"].request_units, 250f64);
+        }
+    }
+
+    #[test]
+    fn load_bill_missing_column() {
+        let file = std::env::temp_dir().join(format!(
+            "serverless-cost-calculator-{}-missing.csv",
+            std::process::id()
+        ));
+        std::fs::write(&file, "cluster,storage\norders,10\n").unwrap();
+        let bill = load_bill(file.to_str().unwrap());
+        std::fs::remove_file(&file).unwrap();
+        assert!(bill.is_err());
+    }
+}
@@ -0,0 +1,160 @@
+use anyhow::Result;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sqlx::{MySql, Pool};
+
+/// Character sets supported by TiDB.
+const CHARSETS: [&str; 7] = [
+    "utf8", "utf8mb3", "utf8mb4", "ascii", "latin1", "binary", "gbk",
+];
+
+/// Collations supported by TiDB with the new collation framework.
+const COLLATIONS: [&str; 16] = [
+    "utf8mb4_bin",
+    "utf8mb4_general_ci",
+    "utf8mb4_unicode_ci",
+    "utf8mb4_0900_ai_ci",
+    "utf8mb4_0900_bin",
+    "utf8_bin",
+    "utf8_general_ci",
+    "utf8_unicode_ci",
+    "utf8mb3_bin",
+    "utf8mb3_general_ci",
+    "utf8mb3_unicode_ci",
+    "ascii_bin",
+    "latin1_bin",
+    "binary",
+    "gbk_bin",
+    "gbk_chinese_ci",
+];
+
+const SPATIAL_TYPES: [&str; 8] = [
+    "geometry",
+    "point",
+    "linestring",
+    "polygon",
+    "multipoint",
+    "multilinestring",
+    "multipolygon",
+    "geometrycollection",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// The schema cannot be migrated as is.
+    Blocker,
+    /// The schema can be migrated but may behave differently.
+    Warning,
+}
+
+impl Severity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Blocker => "blocker",
+            Severity::Warning => "warning",
+        }
+    }
+}
+
+/// A feature of the source schema TiDB Serverless does not support, or
+/// supports differently, with the objects using it.
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+pub struct CompatibilityIssue {
+    pub severity: Severity,
+    pub code: String,
+    pub message: String,
+    pub objects: Vec<String>,
+}
+
+/// The objects found by a query along with the issue they raise.
+struct Check {
+    severity: Severity,
+    code: &'static str,
+    message: &'static str,
+    query: String,
+}
+
+fn quoted(values: &[&str]) -> String {
+    values
+        .iter()
+        .map(|value| format!("'{}'", value))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn checks() -> [Check; 8] {
+    [
+        Check {
+            severity: Severity::Blocker,
+            code: "stored_routine",
+            message: "Stored procedures and functions are not supported, move their logic to the application.",
+            query: "SELECT CONCAT(ROUTINE_TYPE, ' ', ROUTINE_NAME) FROM information_schema.ROUTINES WHERE ROUTINE_SCHEMA=? ORDER BY ROUTINE_NAME".into(),
+        },
+        Check {
+            severity: Severity::Blocker,
+            code: "trigger",
+            message: "Triggers are not supported, move their logic to the application.",
+            query: "SELECT CONCAT(EVENT_OBJECT_TABLE, '.', TRIGGER_NAME) FROM information_schema.TRIGGERS WHERE TRIGGER_SCHEMA=? ORDER BY EVENT_OBJECT_TABLE, TRIGGER_NAME".into(),
+        },
+        Check {
+            severity: Severity::Blocker,
+            code: "event",
+            message: "Events are not supported, schedule the jobs outside of the database.",
+            query: "SELECT EVENT_NAME FROM information_schema.EVENTS WHERE EVENT_SCHEMA=? ORDER BY EVENT_NAME".into(),
+        },
+        Check {
+            severity: Severity::Warning,
+            code: "foreign_key",
+            message: "Foreign keys are enforced since TiDB 6.6 only, verify the constraints and their performance after the migration.",
+            query: "SELECT CONCAT(TABLE_NAME, '.', CONSTRAINT_NAME) FROM information_schema.REFERENTIAL_CONSTRAINTS WHERE CONSTRAINT_SCHEMA=? ORDER BY TABLE_NAME, CONSTRAINT_NAME".into(),
+        },
+        Check {
+            severity: Severity::Blocker,
+            code: "unsupported_charset",
+            message: "The character set is not supported, convert the columns to utf8mb4.",
+            query: format!("SELECT CONCAT(TABLE_NAME, '.', COLUMN_NAME, ' (', CHARACTER_SET_NAME, ')') FROM information_schema.COLUMNS WHERE TABLE_SCHEMA=? AND CHARACTER_SET_NAME IS NOT NULL AND LOWER(CHARACTER_SET_NAME) NOT IN ({}) ORDER BY TABLE_NAME, ORDINAL_POSITION", quoted(&CHARSETS)),
+        },
+        Check {
+            severity: Severity::Warning,
+            code: "unsupported_collation",
+            message: "The collation is not supported and falls back to a supported one, which may sort and compare differently.",
+            query: format!("SELECT CONCAT(TABLE_NAME, '.', COLUMN_NAME, ' (', COLLATION_NAME, ')') FROM information_schema.COLUMNS WHERE TABLE_SCHEMA=? AND COLLATION_NAME IS NOT NULL AND LOWER(CHARACTER_SET_NAME) IN ({}) AND LOWER(COLLATION_NAME) NOT IN ({}) ORDER BY TABLE_NAME, ORDINAL_POSITION", quoted(&CHARSETS), quoted(&COLLATIONS)),
+        },
+        Check {
+            severity: Severity::Blocker,
+            code: "spatial_type",
+            message: "Spatial types are not supported.",
+            query: format!("SELECT CONCAT(TABLE_NAME, '.', COLUMN_NAME, ' (', DATA_TYPE, ')') FROM information_schema.COLUMNS WHERE TABLE_SCHEMA=? AND LOWER(DATA_TYPE) IN ({}) ORDER BY TABLE_NAME, ORDINAL_POSITION", quoted(&SPATIAL_TYPES)),
+        },
+        Check {
+            severity: Severity::Blocker,
+            code: "fulltext_index",
+            message: "Full-text indexes are not supported, use a search service or TiDB full-text search where it is available.",
+            query: "SELECT DISTINCT CONCAT(TABLE_NAME, '.', INDEX_NAME) FROM information_schema.STATISTICS WHERE TABLE_SCHEMA=? AND INDEX_TYPE='FULLTEXT' ORDER BY 1".into(),
+        },
+    ]
+}
+
+/// Scans the schema of the database for features TiDB Serverless does not
+/// support, blockers first.
+pub async fn scan(pool: &Pool<MySql>, database: &str) -> Result<Vec<CompatibilityIssue>> {
+    let mut issues = Vec::new();
+    for check in checks() {
+        let objects: Vec<(String,)> = sqlx::query_as(&check.query)
+            .bind(database)
+            .fetch_all(pool)
+            .await?;
+        if objects.is_empty() {
+            continue;
+        }
+        issues.push(CompatibilityIssue {
+            severity: check.severity,
+            code: check.code.into(),
+            message: check.message.into(),
+            objects: objects.into_iter().map(|(object,)| object).collect(),
+        });
+    }
+    issues.sort_by_key(|issue| issue.severity != Severity::Blocker);
+    Ok(issues)
+}
@@ -0,0 +1,43 @@
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+
+use anyhow::{anyhow, Result};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// Loads a json or yaml configuration file, the format is chosen by the file extension.
+pub fn load<T: DeserializeOwned>(file: &str, kind: &str) -> Result<T> {
+    let extension = file.to_lowercase();
+    let reader = BufReader::new(File::open(file)?);
+    if extension.ends_with(".json") {
+        Ok(serde_json::from_reader(reader)?)
+    } else if extension.ends_with(".yaml") || extension.ends_with(".yml") {
+        Ok(serde_yaml::from_reader(reader)?)
+    } else {
+        Err(anyhow!(
+            "Unknown {} file format. Only json and yaml are supported",
+            kind
+        ))
+    }
+}
+
+/// Saves a value as a json or yaml file, the format is chosen by the file extension.
+pub fn save<T: Serialize>(file: &str, value: &T, kind: &str) -> Result<()> {
+    let extension = file.to_lowercase();
+    if extension.ends_with(".json") {
+        Ok(serde_json::to_writer_pretty(
+            BufWriter::new(File::create(file)?),
+            value,
+        )?)
+    } else if extension.ends_with(".yaml") || extension.ends_with(".yml") {
+        Ok(serde_yaml::to_writer(
+            BufWriter::new(File::create(file)?),
+            value,
+        )?)
+    } else {
+        Err(anyhow!(
+            "Unknown {} file format. Only json and yaml are supported",
+            kind
+        ))
+    }
+}
@@ -0,0 +1,291 @@
+use std::collections::BTreeMap;
+
+use anyhow::{anyhow, Result};
+use colored::Colorize;
+use prettytable::{row, Table};
+
+use crate::config;
+use crate::report::{ClusterReport, Report};
+
+/// The change of a value between the old and the new report.
+#[derive(Debug, Clone, Copy)]
+pub struct Change {
+    pub old: f64,
+    pub new: f64,
+}
+
+impl Change {
+    fn new(old: f64, new: f64) -> Self {
+        Self { old, new }
+    }
+
+    pub fn delta(&self) -> f64 {
+        self.new - self.old
+    }
+
+    /// The relative change in percent, none when there is nothing to compare with.
+    pub fn percent(&self) -> Option<f64> {
+        (self.old != 0f64).then(|| self.delta() * 100f64 / self.old.abs())
+    }
+
+    fn format_percent(&self) -> String {
+        match self.percent() {
+            Some(percent) => format!("{:+.1}%", percent),
+            None if self.delta() == 0f64 => "+0.0%".into(),
+            None => "n/a".into(),
+        }
+    }
+}
+
+fn dollars(cost: f64) -> String {
+    format!("${:.2}", cost)
+}
+
+fn signed_dollars(cost: f64) -> String {
+    if cost < 0f64 {
+        format!("-${:.2}", -cost)
+    } else {
+        format!("+${:.2}", cost)
+    }
+}
+
+pub struct ClusterDiff {
+    pub name: String,
+    pub costs: [(&'static str, Change); 3],
+    pub inputs: [(&'static str, Change); 5],
+}
+
+impl ClusterDiff {
+    fn new(old: &ClusterReport, new: &ClusterReport) -> Self {
+        let storage = |cluster: &ClusterReport| {
+            (cluster.workload.storage_data_in_bytes + cluster.workload.storage_index_in_bytes)
+                as f64
+        };
+        Self {
+            name: new.name.clone(),
+            costs: [
+                (
+                    "Request Units",
+                    Change::new(
+                        old.costs.request_units.expected,
+                        new.costs.request_units.expected,
+                    ),
+                ),
+                (
+                    "Row-based Storage",
+                    Change::new(
+                        old.costs.row_based_storage.expected,
+                        new.costs.row_based_storage.expected,
+                    ),
+                ),
+                (
+                    "Total",
+                    Change::new(old.costs.total.expected, new.costs.total.expected),
+                ),
+            ],
+            inputs: [
+                ("Storage bytes", Change::new(storage(old), storage(new))),
+                (
+                    "Read requests/h",
+                    Change::new(
+                        old.workload.read_requests_per_hour as f64,
+                        new.workload.read_requests_per_hour as f64,
+                    ),
+                ),
+                (
+                    "Write requests/h",
+                    Change::new(
+                        old.workload.write_requests_per_hour as f64,
+                        new.workload.write_requests_per_hour as f64,
+                    ),
+                ),
+                (
+                    "Egress bytes/h",
+                    Change::new(
+                        old.workload.egress_bytes_per_hour as f64,
+                        new.workload.egress_bytes_per_hour as f64,
+                    ),
+                ),
+                (
+                    "RU/h",
+                    Change::new(request_units_per_hour(old), request_units_per_hour(new)),
+                ),
+            ],
+        }
+    }
+
+    pub fn total(&self) -> Change {
+        self.costs[2].1
+    }
+}
+
+/// The measured request units per hour, or the ones derived from the monthly usage.
+fn request_units_per_hour(cluster: &ClusterReport) -> f64 {
+    cluster
+        .workload
+        .request_units_per_hour
+        .map(|request_units| request_units as f64)
+        .unwrap_or_else(|| {
+            cluster.usage.request_units_in_million as f64 * 1024f64 * 1024f64 / 730f64
+        })
+}
+
+/// Indexes the clusters of a report by name, the clusters are matched by name
+/// so a name appearing twice cannot be compared.
+fn clusters_by_name<'a>(
+    report: &'a Report,
+    label: &str,
+) -> Result<BTreeMap<&'a str, &'a ClusterReport>> {
+    let mut clusters = BTreeMap::new();
+    for cluster in &report.clusters {
+        if clusters.insert(cluster.name.as_str(), cluster).is_some() {
+            return Err(anyhow!(
+                "The cluster '{}' appears more than once in the {} report, clusters are compared by name",
+                cluster.name,
+                label
+            ));
+        }
+    }
+    Ok(clusters)
+}
+
+pub struct ReportDiff {
+    pub clusters: Vec<ClusterDiff>,
+    pub added: Vec<(String, f64)>,
+    pub removed: Vec<(String, f64)>,
+    pub total: Change,
+    pub warnings: Vec<String>,
+}
+
+impl ReportDiff {
+    pub fn new(old: &Report, new: &Report) -> Result<Self> {
+        let mut warnings = Vec::new();
+        for (field, old, new) in [
+            (
+                "schema version",
+                &old.schema_version.to_string(),
+                &new.schema_version.to_string(),
+            ),
+            ("region", &old.region, &new.region),
+            (
+                "pricing version",
+                &old.pricing_version,
+                &new.pricing_version,
+            ),
+            ("model", &old.model.name, &new.model.name),
+            ("model version", &old.model.version, &new.model.version),
+        ] {
+            if old != new {
+                warnings.push(format!(
+                    "The {} changed from '{}' to '{}', costs may not be comparable.",
+                    field, old, new
+                ));
+            }
+        }
+
+        let old_clusters = clusters_by_name(old, "old")?;
+        let new_clusters = clusters_by_name(new, "new")?;
+        let total = |clusters: &BTreeMap<&str, &ClusterReport>| -> f64 {
+            clusters
+                .values()
+                .map(|cluster| cluster.costs.total.expected)
+                .sum()
+        };
+        Ok(Self {
+            clusters: new_clusters
+                .iter()
+                .filter_map(|(name, new)| {
+                    old_clusters.get(name).map(|old| ClusterDiff::new(old, new))
+                })
+                .collect(),
+            added: new_clusters
+                .iter()
+                .filter(|(name, _)| !old_clusters.contains_key(*name))
+                .map(|(name, cluster)| (name.to_string(), cluster.costs.total.expected))
+                .collect(),
+            removed: old_clusters
+                .iter()
+                .filter(|(name, _)| !new_clusters.contains_key(*name))
+                .map(|(name, cluster)| (name.to_string(), cluster.costs.total.expected))
+                .collect(),
+            total: Change::new(total(&old_clusters), total(&new_clusters)),
+            warnings,
+        })
+    }
+
+    /// Describes every cost increase beyond the threshold in percent, for the
+    /// clusters found in both reports and the total of all clusters.
+    pub fn increases_beyond(&self, threshold: f64) -> Vec<String> {
+        self.clusters
+            .iter()
+            .map(|cluster| (cluster.name.as_str(), cluster.total()))
+            .chain([("all clusters", self.total)])
+            .filter(|(_, change)| {
+                change.delta() > 0f64 && change.percent().is_none_or(|p| p > threshold)
+            })
+            .map(|(name, change)| {
+                format!(
+                    "The monthly cost of {} increased by {} ({}), beyond the threshold of {}%",
+                    name,
+                    signed_dollars(change.delta()),
+                    change.format_percent(),
+                    threshold
+                )
+            })
+            .collect()
+    }
+
+    pub fn print(&self) {
+        println!(
+            "The estimated monthly cost changed from {} to {} ({}, {})",
+            dollars(self.total.old).bold().green(),
+            dollars(self.total.new).bold().green(),
+            signed_dollars(self.total.delta()).bold().green(),
+            self.total.format_percent().bold().green()
+        );
+        for cluster in &self.clusters {
+            println!("Cluster: {}", cluster.name.bold().green());
+            let mut table = Table::new();
+            table.set_titles(
+                row![bFg -> "SKU", bFgr -> "Old", bFgr -> "New", bFgr -> "Delta", bFgr -> "Change"],
+            );
+            for (sku, change) in &cluster.costs {
+                table.add_row(row![bFg -> sku,
+                    bFgr -> dollars(change.old),
+                    bFgr -> dollars(change.new),
+                    bFgr -> signed_dollars(change.delta()),
+                    bFgr -> change.format_percent()]);
+            }
+            table.printstd();
+            let mut table = Table::new();
+            table.set_titles(
+                row![bFg -> "Input", bFgr -> "Old", bFgr -> "New", bFgr -> "Delta", bFgr -> "Change"],
+            );
+            for (input, change) in &cluster.inputs {
+                table.add_row(row![Fg -> input,
+                    Fgr -> format!("{:.0}", change.old),
+                    Fgr -> format!("{:.0}", change.new),
+                    Fgr -> format!("{:+.0}", change.delta()),
+                    Fgr -> change.format_percent()]);
+            }
+            table.printstd();
+        }
+        if !self.added.is_empty() || !self.removed.is_empty() {
+            let mut table = Table::new();
+            table.set_titles(row![bFg -> "Cluster", bFg -> "Change", bFgr -> "Total"]);
+            for (name, cost) in &self.added {
+                table.add_row(row![Fg -> name, Fg -> "new", Fgr -> dollars(*cost)]);
+            }
+            for (name, cost) in &self.removed {
+                table.add_row(row![Fg -> name, Fg -> "removed", Fgr -> dollars(*cost)]);
+            }
+            table.printstd();
+        }
+    }
+}
+
+pub fn diff(old: &str, new: &str) -> Result<ReportDiff> {
+    let old: Report = config::load(old, "report")?;
+    let new: Report = config::load(new, "report")?;
+    ReportDiff::new(&old, &new)
+}
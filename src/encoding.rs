@@ -0,0 +1,349 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+/// `t`, the table id and `_r` prefixing the record keys.
+const RECORD_PREFIX: u64 = 11;
+/// `t`, the table id, `_i` and the index id prefixing the index keys.
+const INDEX_PREFIX: u64 = 19;
+/// An integer handle, memcomparable encoded with its flag.
+const INT_HANDLE: u64 = 9;
+/// Version, flags, not null and null column counts of a row format v2 value.
+const ROW_HEADER: u64 = 6;
+/// Column id and end offset of every column of a small row format v2 value.
+const COLUMN_OVERHEAD: u64 = 3;
+/// Value of a non-unique index entry.
+const INDEX_VALUE: u64 = 1;
+/// Record header, transaction id and roll pointer of an InnoDB record.
+const INNODB_RECORD_OVERHEAD: u64 = 18;
+/// Average fraction of the InnoDB pages filled with records.
+const INNODB_FILL_FACTOR: f64 = 0.85;
+
+/// A column as described by `information_schema.COLUMNS`.
+#[derive(Debug, Clone, FromRow)]
+pub struct ColumnDefinition {
+    pub table_name: String,
+    pub name: String,
+    pub data_type: String,
+    pub numeric_precision: Option<u64>,
+}
+
+/// A column of an index as described by `information_schema.STATISTICS`.
+#[derive(Debug, Clone, FromRow)]
+pub struct IndexColumn {
+    pub table_name: String,
+    pub index_name: String,
+    pub non_unique: i64,
+    pub column_name: String,
+    pub sub_part: Option<u64>,
+}
+
+/// The definition and statistics of a table whose encoded size is estimated.
+#[derive(Debug, Default)]
+pub struct TableSchema {
+    pub name: String,
+    pub rows: u64,
+    pub data_in_bytes: u64,
+    pub index_in_bytes: u64,
+    pub columns: Vec<ColumnDefinition>,
+    pub indexes: Vec<IndexColumn>,
+}
+
+/// The size of a column in the InnoDB record, in the row format v2 value and
+/// memcomparable encoded in a key.
+#[derive(Debug, Clone, Copy)]
+struct ColumnSize {
+    mysql: u64,
+    row: u64,
+    key: u64,
+}
+
+impl ColumnSize {
+    /// Sizes of the fixed length types, none for the variable length ones.
+    fn fixed(column: &ColumnDefinition) -> Option<Self> {
+        let size = |mysql, row| ColumnSize {
+            mysql,
+            row,
+            key: INT_HANDLE,
+        };
+        Some(match column.data_type.to_lowercase().as_str() {
+            "tinyint" | "bool" | "boolean" => size(1, 1),
+            "smallint" => size(2, 2),
+            "mediumint" => size(3, 4),
+            "int" | "integer" => size(4, 4),
+            "bigint" => size(8, 8),
+            "float" => size(4, 8),
+            "double" | "real" => size(8, 8),
+            "year" => size(1, 2),
+            "date" => size(3, 8),
+            "time" => size(3, 8),
+            "timestamp" => size(4, 8),
+            "datetime" => size(5, 8),
+            "enum" => size(2, 2),
+            "set" => size(8, 8),
+            "bit" => {
+                let bytes = column.numeric_precision.unwrap_or(1).div_ceil(8);
+                size(bytes, bytes)
+            }
+            "decimal" | "numeric" => {
+                let digits = column.numeric_precision.unwrap_or(10);
+                let bytes = digits / 9 * 4 + (digits % 9).div_ceil(2);
+                ColumnSize {
+                    mysql: bytes,
+                    row: bytes + 2,
+                    key: bytes + 3,
+                }
+            }
+            _ => return None,
+        })
+    }
+
+    /// Variable length data stored as is in the row, and in groups of eight
+    /// bytes followed by a marker in a key.
+    fn variable(bytes: u64) -> Self {
+        ColumnSize {
+            mysql: bytes,
+            row: bytes,
+            key: (bytes / 8 + 1) * 9,
+        }
+    }
+}
+
+/// The size of a table on the source and estimated for TiDB Serverless.
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize)]
+pub struct EncodedSize {
+    pub mysql_data_in_bytes: u64,
+    pub mysql_index_in_bytes: u64,
+    pub tidb_data_in_bytes: u64,
+    pub tidb_index_in_bytes: u64,
+}
+
+impl EncodedSize {
+    pub fn add(&mut self, other: &EncodedSize) {
+        self.mysql_data_in_bytes += other.mysql_data_in_bytes;
+        self.mysql_index_in_bytes += other.mysql_index_in_bytes;
+        self.tidb_data_in_bytes += other.tidb_data_in_bytes;
+        self.tidb_index_in_bytes += other.tidb_index_in_bytes;
+    }
+
+    fn ratio(tidb: u64, mysql: u64) -> f64 {
+        if mysql > 0 {
+            tidb as f64 / mysql as f64
+        } else {
+            1f64
+        }
+    }
+
+    /// The TiDB data size per byte of MySQL data.
+    pub fn data_ratio(&self) -> f64 {
+        Self::ratio(self.tidb_data_in_bytes, self.mysql_data_in_bytes)
+    }
+
+    /// The TiDB index size per byte of MySQL indexes.
+    pub fn index_ratio(&self) -> f64 {
+        Self::ratio(self.tidb_index_in_bytes, self.mysql_index_in_bytes)
+    }
+}
+
+impl TableSchema {
+    /// Sizes of every column. The variable length columns share the bytes of
+    /// the average InnoDB record left after the fixed length columns.
+    fn column_sizes(&self) -> Vec<(&str, ColumnSize)> {
+        let fixed: Vec<Option<ColumnSize>> = self.columns.iter().map(ColumnSize::fixed).collect();
+        let fixed_in_bytes: u64 = fixed.iter().flatten().map(|size| size.mysql).sum();
+        let variable_columns = fixed.iter().filter(|size| size.is_none()).count() as u64;
+        let average_record_in_bytes =
+            (self.data_in_bytes as f64 * INNODB_FILL_FACTOR) as u64 / self.rows.max(1);
+        let variable_in_bytes = average_record_in_bytes
+            .saturating_sub(INNODB_RECORD_OVERHEAD + fixed_in_bytes)
+            / variable_columns.max(1);
+        self.columns
+            .iter()
+            .zip(fixed)
+            .map(|(column, size)| {
+                (
+                    column.name.as_str(),
+                    size.unwrap_or_else(|| ColumnSize::variable(variable_in_bytes)),
+                )
+            })
+            .collect()
+    }
+
+    /// Estimates the size of the table encoded by TiDB: a record per row keyed
+    /// by its handle, clustered by the primary key, and an entry per row for
+    /// every other index.
+    pub fn encoded_size(&self) -> EncodedSize {
+        let sizes = self.column_sizes();
+        let key_size = |column: &IndexColumn| {
+            sizes
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(&column.column_name))
+                .map_or(INT_HANDLE, |(_, size)| match column.sub_part {
+                    Some(prefix) => size.key.min(ColumnSize::variable(prefix).key),
+                    None => size.key,
+                })
+        };
+        let mut indexes: Vec<(&str, bool, Vec<&IndexColumn>)> = Vec::new();
+        for column in &self.indexes {
+            match indexes
+                .iter_mut()
+                .find(|(name, _, _)| *name == column.index_name)
+            {
+                Some((_, _, columns)) => columns.push(column),
+                None => indexes.push((&column.index_name, column.non_unique == 0, vec![column])),
+            }
+        }
+
+        let handle = indexes
+            .iter()
+            .find(|(name, _, _)| name.eq_ignore_ascii_case("PRIMARY"))
+            .map_or(INT_HANDLE, |(_, _, columns)| {
+                columns.iter().map(|column| key_size(column)).sum()
+            });
+        let record = RECORD_PREFIX
+            + handle
+            + ROW_HEADER
+            + sizes
+                .iter()
+                .map(|(_, size)| COLUMN_OVERHEAD + size.row)
+                .sum::<u64>();
+        let index_entries: u64 = indexes
+            .iter()
+            .filter(|(name, _, _)| !name.eq_ignore_ascii_case("PRIMARY"))
+            .map(|(_, unique, columns)| {
+                let key = INDEX_PREFIX + columns.iter().map(|column| key_size(column)).sum::<u64>();
+                if *unique {
+                    key + handle
+                } else {
+                    key + handle + INDEX_VALUE
+                }
+            })
+            .sum();
+        EncodedSize {
+            mysql_data_in_bytes: self.data_in_bytes,
+            mysql_index_in_bytes: self.index_in_bytes,
+            tidb_data_in_bytes: self.rows * record,
+            tidb_index_in_bytes: self.rows * index_entries,
+        }
+    }
+}
+
+/// Estimates the encoded size of all tables.
+pub fn encoded_size(tables: &[TableSchema]) -> EncodedSize {
+    let mut size = EncodedSize::default();
+    for table in tables {
+        size.add(&table.encoded_size());
+    }
+    size
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn column(name: &str, data_type: &str) -> ColumnDefinition {
+        ColumnDefinition {
+            table_name: "t".into(),
+            name: name.into(),
+            data_type: data_type.into(),
+            numeric_precision: None,
+        }
+    }
+
+    fn index(name: &str, unique: bool, column: &str, sub_part: Option<u64>) -> IndexColumn {
+        IndexColumn {
+            table_name: "t".into(),
+            index_name: name.into(),
+            non_unique: i64::from(!unique),
+            column_name: column.into(),
+            sub_part,
+        }
+    }
+
+    /// Ten rows of an int id and a varchar, whose 2000 bytes leave 148 bytes
+    /// of the average record to the varchar.
+    fn table(indexes: Vec<IndexColumn>) -> TableSchema {
+        TableSchema {
+            name: "t".into(),
+            rows: 10,
+            data_in_bytes: 2000,
+            index_in_bytes: 500,
+            columns: vec![column("id", "int"), column("name", "varchar")],
+            indexes,
+        }
+    }
+
+    #[test]
+    fn int_primary_key_versus_clustered_composite_key() {
+        let int_key = table(vec![
+            index("PRIMARY", true, "id", None),
+            index("idx_name", false, "name", Some(10)),
+        ])
+        .encoded_size();
+        // Prefix, handle, header, then the int and varchar with their overhead.
+        assert_eq!(int_key.tidb_data_in_bytes, 10 * (11 + 9 + 6 + 7 + 151));
+        assert_eq!(int_key.tidb_index_in_bytes, 10 * (19 + 18 + 9 + 1));
+        assert_eq!(int_key.mysql_data_in_bytes, 2000);
+        assert_eq!(int_key.mysql_index_in_bytes, 500);
+
+        // The handle of a clustered key holds all of its columns, in the
+        // record and in every secondary index entry.
+        let composite_key = table(vec![
+            index("PRIMARY", true, "id", None),
+            index("PRIMARY", true, "name", None),
+            index("idx_name", false, "name", Some(10)),
+        ])
+        .encoded_size();
+        let handle = 9 + (148 / 8 + 1) * 9;
+        assert_eq!(
+            composite_key.tidb_data_in_bytes,
+            10 * (11 + handle + 6 + 7 + 151)
+        );
+        assert_eq!(
+            composite_key.tidb_index_in_bytes,
+            10 * (19 + 18 + handle + 1)
+        );
+    }
+
+    #[test]
+    fn unique_versus_non_unique_index() {
+        let unique = table(vec![index("idx_id", true, "id", None)]).encoded_size();
+        let non_unique = table(vec![index("idx_id", false, "id", None)]).encoded_size();
+        assert_eq!(unique.tidb_index_in_bytes, 10 * (19 + 9 + 9));
+        assert_eq!(non_unique.tidb_index_in_bytes, 10 * (19 + 9 + 9 + 1));
+    }
+
+    #[test]
+    fn prefix_index() {
+        let full = table(vec![index("idx_name", false, "name", None)]).encoded_size();
+        let prefix = table(vec![index("idx_name", false, "name", Some(10))]).encoded_size();
+        assert_eq!(
+            full.tidb_index_in_bytes,
+            10 * (19 + (148 / 8 + 1) * 9 + 9 + 1)
+        );
+        assert_eq!(
+            prefix.tidb_index_in_bytes,
+            10 * (19 + (10 / 8 + 1) * 9 + 9 + 1)
+        );
+        // A prefix longer than the column keeps the size of the column.
+        let long = table(vec![index("idx_name", false, "name", Some(1000))]).encoded_size();
+        assert_eq!(long.tidb_index_in_bytes, full.tidb_index_in_bytes);
+    }
+
+    #[test]
+    fn variable_length_columns_share_the_record() {
+        let mut schema = table(Vec::new());
+        schema.columns.push(column("body", "text"));
+        // (170 bytes per record - 18 overhead - 4 for the int) / 2 columns.
+        assert_eq!(
+            schema.encoded_size().tidb_data_in_bytes,
+            10 * (11 + 9 + 6 + 7 + 2 * (3 + 74))
+        );
+
+        // Fixed length columns larger than the records leave nothing to share.
+        schema.data_in_bytes = 100;
+        assert_eq!(
+            schema.encoded_size().tidb_data_in_bytes,
+            10 * (11 + 9 + 6 + 7 + 2 * 3)
+        );
+    }
+}
@@ -0,0 +1,139 @@
+use serde::ser::SerializeMap;
+use serde::{Serialize, Serializer};
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(untagged)]
+pub enum Value {
+    Integer(u64),
+    Decimal(f64),
+}
+
+impl From<u64> for Value {
+    fn from(value: u64) -> Self {
+        Value::Integer(value)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(value: f64) -> Self {
+        Value::Decimal(value)
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Integer(value) => write!(f, "{}", value),
+            Value::Decimal(value) => write!(f, "{:.4}", value),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Inputs(Vec<(String, Value)>);
+
+impl Inputs {
+    fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl Serialize for Inputs {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+        for (name, value) in &self.0 {
+            map.serialize_entry(name, value)?;
+        }
+        map.end()
+    }
+}
+
+/// A node of the trace explaining how an estimation is derived. Leaf nodes
+/// record a single computed value along with its formula and inputs, inner
+/// nodes group the steps of a stage.
+#[derive(Debug, Default, Serialize)]
+pub struct Trace {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    formula: Option<String>,
+    #[serde(skip_serializing_if = "Inputs::is_empty")]
+    inputs: Inputs,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    value: Option<Value>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    steps: Vec<Trace>,
+}
+
+impl Trace {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn step<V: Into<Value> + Copy>(
+        &mut self,
+        name: &str,
+        formula: &str,
+        inputs: &[(&str, Value)],
+        value: V,
+    ) -> V {
+        self.steps.push(Trace {
+            name: name.into(),
+            formula: Some(formula.into()),
+            inputs: Inputs(
+                inputs
+                    .iter()
+                    .map(|(name, value)| (name.to_string(), *value))
+                    .collect(),
+            ),
+            value: Some(value.into()),
+            steps: Vec::new(),
+        });
+        value
+    }
+
+    pub fn push(&mut self, trace: Trace) {
+        self.steps.push(trace);
+    }
+
+    fn fmt_steps(&self, f: &mut Formatter<'_>, prefix: &str) -> std::fmt::Result {
+        for (index, step) in self.steps.iter().enumerate() {
+            let last = index + 1 == self.steps.len();
+            let (branch, indent) = if last {
+                ("└── ", "    ")
+            } else {
+                ("├── ", "│   ")
+            };
+            match step.value {
+                Some(value) => writeln!(f, "{}{}{} = {}", prefix, branch, step.name, value)?,
+                None => writeln!(f, "{}{}{}", prefix, branch, step.name)?,
+            }
+            let detail = format!("{}{}", prefix, indent);
+            if let Some(formula) = &step.formula {
+                writeln!(f, "{}formula: {}", detail, formula)?;
+            }
+            if !step.inputs.is_empty() {
+                let inputs: Vec<String> = step
+                    .inputs
+                    .0
+                    .iter()
+                    .map(|(name, value)| format!("{} = {}", name, value))
+                    .collect();
+                writeln!(f, "{}inputs: {}", detail, inputs.join(", "))?;
+            }
+            step.fmt_steps(f, &detail)?;
+        }
+        Ok(())
+    }
+}
+
+/// Renders the trace as a tree, one step per line.
+impl Display for Trace {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{}", self.name)?;
+        self.fmt_steps(f, "")
+    }
+}
@@ -0,0 +1,163 @@
+use std::collections::BTreeMap;
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use colored::Colorize;
+use prettytable::{row, Table};
+use readable::num::Unsigned;
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool};
+use sqlx::FromRow;
+
+use crate::calculator::WorkloadEstimation;
+use crate::source::WorkloadDescription;
+
+const SPARKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+const SCHEMA: &str = "CREATE TABLE IF NOT EXISTS runs (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    cluster TEXT NOT NULL,
+    region TEXT NOT NULL,
+    collected_at TEXT NOT NULL,
+    total_cost REAL NOT NULL,
+    request_units_in_million INTEGER NOT NULL,
+    row_based_storage_in_mib INTEGER NOT NULL,
+    network_egress_in_mib INTEGER NOT NULL,
+    workload TEXT NOT NULL,
+    estimation TEXT NOT NULL
+)";
+
+#[derive(Debug, FromRow)]
+pub struct Run {
+    pub cluster: String,
+    pub region: String,
+    pub collected_at: DateTime<Utc>,
+    pub total_cost: f64,
+    pub request_units_in_million: i64,
+    pub row_based_storage_in_mib: i64,
+}
+
+async fn open(file: &str) -> Result<SqlitePool> {
+    let pool = SqlitePool::connect_with(
+        SqliteConnectOptions::new()
+            .filename(file)
+            .create_if_missing(true),
+    )
+    .await?;
+    sqlx::query(SCHEMA).execute(&pool).await?;
+    Ok(pool)
+}
+
+/// Appends the collected workloads and their estimations to the history file.
+pub async fn record(
+    file: &str,
+    region: &str,
+    workloads: &[WorkloadDescription],
+    estimations: &[WorkloadEstimation],
+) -> Result<()> {
+    let pool = open(file).await?;
+    let mut transaction = pool.begin().await?;
+    for (workload, estimation) in workloads.iter().zip(estimations) {
+        sqlx::query("INSERT INTO runs (cluster, region, collected_at, total_cost, request_units_in_million, row_based_storage_in_mib, network_egress_in_mib, workload, estimation) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)")
+            .bind(&workload.name)
+            .bind(region)
+            .bind(workload.collected_at)
+            .bind(estimation.total_cost())
+            .bind(estimation.usage.request_units_in_million as i64)
+            .bind(estimation.usage.row_based_storage_in_mib as i64)
+            .bind(estimation.usage.network_egress_in_mib as i64)
+            .bind(serde_json::to_string(workload)?)
+            .bind(serde_json::to_string(estimation)?)
+            .execute(&mut *transaction)
+            .await?;
+    }
+    transaction.commit().await?;
+    pool.close().await;
+    Ok(())
+}
+
+/// Reads the latest runs of every cluster, or of the given one, oldest first.
+pub async fn load(file: &str, cluster: Option<&str>, limit: u32) -> Result<Vec<Run>> {
+    if !std::path::Path::new(file).exists() {
+        return Err(anyhow!("The history file '{}' does not exist", file));
+    }
+    let pool = open(file).await?;
+    let runs = sqlx::query_as("SELECT cluster, region, collected_at, total_cost, request_units_in_million, row_based_storage_in_mib FROM (SELECT *, ROW_NUMBER() OVER (PARTITION BY cluster ORDER BY collected_at DESC) AS position FROM runs WHERE ? IS NULL OR cluster = ?) WHERE position <= ? ORDER BY cluster, collected_at")
+        .bind(cluster)
+        .bind(cluster)
+        .bind(limit)
+        .fetch_all(&pool)
+        .await?;
+    pool.close().await;
+    Ok(runs)
+}
+
+/// Draws the values as a sparkline scaled between their minimum and maximum.
+fn sparkline(values: &[f64]) -> String {
+    let min = values.iter().cloned().fold(f64::INFINITY, f64::min);
+    let max = values.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    values
+        .iter()
+        .map(|value| {
+            if max > min {
+                let level = (value - min) / (max - min) * (SPARKS.len() - 1) as f64;
+                SPARKS[level.round() as usize]
+            } else {
+                SPARKS[0]
+            }
+        })
+        .collect()
+}
+
+pub fn print(runs: &[Run]) {
+    let mut clusters: BTreeMap<&str, Vec<&Run>> = BTreeMap::new();
+    for run in runs {
+        clusters.entry(&run.cluster).or_default().push(run);
+    }
+    for (cluster, runs) in clusters {
+        println!(
+            "Cluster: {} ({} run(s))",
+            cluster.bold().green(),
+            runs.len()
+        );
+        let mut table = Table::new();
+        table.set_titles(
+            row![bFg -> "Collected at", bFg -> "Region", bFgr -> "Total", bFgr -> "RU (million)", bFgr -> "Storage (MiB)"],
+        );
+        for run in &runs {
+            table.add_row(row![Fg -> run.collected_at.format("%Y-%m-%d %H:%M"),
+                Fg -> run.region,
+                Fgr -> format!("${:.2}", run.total_cost),
+                Fgr -> Unsigned::from(run.request_units_in_million as u64),
+                Fgr -> Unsigned::from(run.row_based_storage_in_mib as u64)]);
+        }
+        table.printstd();
+
+        let mut table = Table::new();
+        table.set_titles(row![bFg -> "Trend", bFg -> "", bFgr -> "First", bFgr -> "Last"]);
+        let trends: [(&str, Vec<f64>); 3] = [
+            (
+                "Monthly cost ($)",
+                runs.iter().map(|run| run.total_cost).collect(),
+            ),
+            (
+                "RU (million)",
+                runs.iter()
+                    .map(|run| run.request_units_in_million as f64)
+                    .collect(),
+            ),
+            (
+                "Storage (MiB)",
+                runs.iter()
+                    .map(|run| run.row_based_storage_in_mib as f64)
+                    .collect(),
+            ),
+        ];
+        for (trend, values) in trends {
+            table.add_row(row![Fg -> trend,
+                Fg -> sparkline(&values),
+                Fgr -> format!("{:.2}", values.first().cloned().unwrap_or_default()),
+                Fgr -> format!("{:.2}", values.last().cloned().unwrap_or_default())]);
+        }
+        table.printstd();
+    }
+}
@@ -0,0 +1,44 @@
+//! Estimates the cost of TiDB Serverless for existing MySQL-compatible databases.
+//!
+//! The library collects the workload of a database, or converts a hand-written
+//! specification, estimates its monthly cost and builds the versioned report
+//! rendered by the command line tool. It never prints nor exits, errors are
+//! returned and the diagnostics raised while collecting a workload are handed
+//! to a [`Diagnostics`] implementation chosen by the caller.
+//!
+//! ```no_run
+//! use serverless_cost_calculator::model::RequestUnitModel;
+//! use serverless_cost_calculator::{
+//!     estimate, load_workload_description, LoadOptions, Report, WorkloadSourceConfiguration,
+//! };
+//!
+//! # async fn run() -> anyhow::Result<()> {
+//! let model = RequestUnitModel::default();
+//! let configuration = WorkloadSourceConfiguration::new("localhost", 3306, "root", "", "test");
+//! let workloads: Vec<_> = load_workload_description(&(), &model, configuration, &LoadOptions::default())
+//!     .await?
+//!     .into_iter()
+//!     .collect();
+//! let estimations = estimate("us-east-1", &model, &workloads)?;
+//! let report = Report::new("us-east-1", &model, workloads, estimations, false);
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod calculator;
+pub mod compatibility;
+pub mod config;
+pub mod encoding;
+pub mod explain;
+pub mod model;
+pub mod provider;
+pub mod report;
+pub mod scenario;
+pub mod source;
+
+pub use calculator::{estimate, InvalidRegion, WorkloadEstimation};
+pub use report::Report;
+pub use source::{
+    load_workload_description, AnalyzeOptions, Diagnostics, LoadOptions, ReplicaConfiguration,
+    SourceError, StorageMode, StorageOptions, WorkloadDescription, WorkloadSourceConfiguration,
+};
@@ -1,10 +1,27 @@
-mod calculator;
+mod calibration;
+mod diff;
+mod history;
 mod output;
-mod source;
+mod serve;
+mod tui;
 
-use crate::output::OutputFormat;
-use crate::source::WorkloadSourceConfiguration;
-use clap::{ArgAction, Parser};
+use serverless_cost_calculator::{
+    calculator, compatibility, config, model, report, scenario, source,
+};
+
+use crate::model::RequestUnitModel;
+use crate::output::{ExitCode, OutputFormat};
+use crate::report::Report;
+use crate::scenario::Scenario;
+use crate::source::{
+    AnalyzeOptions, LoadOptions, ReplicaConfiguration, StorageMode, StorageOptions,
+    WorkloadSourceConfiguration,
+};
+use std::num::NonZeroUsize;
+use std::process::exit;
+use std::time::Duration;
+
+use clap::{ArgAction, Args, Parser, Subcommand};
 
 #[derive(Parser)]
 #[command(
@@ -12,6 +29,8 @@ use clap::{ArgAction, Parser};
     version,
     arg_required_else_help(true),
     disable_help_flag(true),
+    subcommand_negates_reqs(true),
+    args_conflicts_with_subcommands(true),
     about = "Estimate the cost of TiDB Serverless for your existing MySQL-compatible databases."
 )]
 struct CalculatorOptions {
@@ -62,9 +81,25 @@ struct CalculatorOptions {
         env = "DB_DATABASE",
         help = "Sets the database for the MySQL server",
         num_args(1),
-        required(true)
+        required_unless_present = "workload"
+    )]
+    database: Option<String>,
+    #[arg(
+        id = "replica",
+        long = "replica",
+        help = "Adds the reads of a replica, as host or host:port, to those of the MySQL server, with the same user and password",
+        num_args(1),
+        action = ArgAction::Append
     )]
-    database: String,
+    replicas: Vec<ReplicaConfiguration>,
+    #[arg(
+        id = "shard",
+        long = "shard",
+        help = "Consolidates the shards at these hosts, as host or host:port with ranges such as shard-[01-32], into a single cluster instead of the MySQL server",
+        num_args(1),
+        action = ArgAction::Append
+    )]
+    shards: Vec<String>,
     #[arg(
         id = "region",
         short = 'r',
@@ -80,20 +115,138 @@ struct CalculatorOptions {
         short = 'a',
         long = "analyze",
         env = "DB_ANALYZE",
+        value_enum,
+        default_value_t = AnalyzeMode::No,
+        default_missing_value = "prompt",
+        num_args(0..=1),
+        help = "Run ANALYZE on the tables with stale statistics before reading system tables depending on them",
+    )]
+    analyze: AnalyzeMode,
+    #[arg(
+        id = "analyze-max-table-size",
+        long = "analyze-max-table-size",
+        env = "DB_ANALYZE_MAX_TABLE_SIZE",
+        help = "Skip tables larger than this size in GiB when running ANALYZE",
+        num_args(1)
+    )]
+    analyze_max_table_size: Option<f64>,
+    #[arg(
+        id = "analyze-pause",
+        long = "analyze-pause",
+        env = "DB_ANALYZE_PAUSE",
+        default_value_t = 0f64,
+        help = "Seconds to pause between two tables when running ANALYZE",
+        num_args(1)
+    )]
+    analyze_pause: f64,
+    #[arg(
+        id = "analyze-dry-run",
+        long = "analyze-dry-run",
+        action = ArgAction::SetTrue,
+        default_value_t = false,
+        help = "List the tables ANALYZE would run on without running it"
+    )]
+    analyze_dry_run: bool,
+    #[arg(
+        id = "storage-measurement",
+        long = "storage-measurement",
+        env = "STORAGE_MEASUREMENT",
+        value_enum,
+        default_value_t = StorageMeasurementMode::Statistics,
+        help = "Measure the storage from the table statistics, or exactly from the InnoDB tablespaces or index pages and counted rows",
+        num_args(1)
+    )]
+    storage_measurement: StorageMeasurementMode,
+    #[arg(
+        id = "row-count-limit",
+        long = "row-count-limit",
+        env = "ROW_COUNT_LIMIT",
+        default_value_t = 1_000_000,
+        help = "Maximum number of rows counted per table by the exact storage measurement",
+        num_args(1)
+    )]
+    row_count_limit: u64,
+    #[arg(
+        id = "storage-conversion",
+        long = "storage-conversion",
+        env = "STORAGE_CONVERSION",
         action = ArgAction::SetTrue,
         default_value_t = false,
-        help = "Run ANALYZE before reading system tables depending on statistics data",
+        help = "Convert the MySQL storage to the size estimated for the TiDB encoding from the column and index definitions"
     )]
-    analyze: bool,
+    storage_conversion: bool,
+    #[arg(
+        id = "compatibility",
+        long = "compatibility",
+        env = "COMPATIBILITY",
+        action = ArgAction::SetTrue,
+        default_value_t = false,
+        help = "Scan the source schema for features TiDB Serverless does not support and report them along with the estimation"
+    )]
+    compatibility: bool,
+    #[arg(
+        id = "serverless",
+        short = 'S',
+        long = "serverless",
+        env = "ESTIMATE_SERVERLESS",
+        action = ArgAction::SetTrue,
+        default_value_t = false,
+        help = "Estimate TiDB Serverless clusters from their own statement summary instead of skipping them",
+    )]
+    serverless: bool,
     #[arg(
         id = "output",
         short = 'o',
         long = "output",
         env = "OUTPUT",
         default_value = "human",
-        help = "Output format. One of: json|yaml|human"
+        help = "Output format. One of: human|json|yaml|csv|markdown|html|openmetrics"
     )]
     output: OutputFormat,
+    #[arg(
+        id = "output-file",
+        short = 'f',
+        long = "output-file",
+        env = "OUTPUT_FILE",
+        help = "Write the report to the file atomically instead of the standard output, e.g. for the node exporter textfile collector",
+        num_args(1)
+    )]
+    output_file: Option<String>,
+    #[arg(
+        id = "history",
+        long = "history",
+        env = "HISTORY",
+        help = "SQLite file every collected workload and estimation is appended to, for the history command",
+        num_args(1)
+    )]
+    history: Option<String>,
+    #[arg(
+        id = "scenarios",
+        long = "scenarios",
+        env = "SCENARIOS",
+        help = "Scenario file, json or yaml, with what-if changes estimated side by side with the collected workload",
+        num_args(1)
+    )]
+    scenarios: Option<String>,
+    #[arg(
+        id = "explain",
+        short = 'e',
+        long = "explain",
+        env = "EXPLAIN",
+        action = ArgAction::SetTrue,
+        default_value_t = false,
+        help = "Trace every intermediate value of the estimation along with its formula and inputs",
+    )]
+    explain: bool,
+    #[arg(
+        id = "interactive",
+        short = 'i',
+        long = "interactive",
+        action = ArgAction::SetTrue,
+        default_value_t = false,
+        help = "Explore the estimations in an interactive terminal UI, adjusting region, traffic, storage growth and spending limit",
+    )]
+    interactive: bool,
     #[arg(
         id = "batch",
         short = 'b',
@@ -102,57 +255,509 @@ struct CalculatorOptions {
         help = "Batch configuration file for upstream databases."
     )]
     batch: Option<String>,
+    #[arg(
+        id = "workload",
+        short = 'w',
+        long = "workload",
+        env = "WORKLOAD",
+        conflicts_with = "batch",
+        help = "Hand-written workload specification file, json or yaml, estimated without connecting to any database"
+    )]
+    workload: Option<String>,
+    #[arg(
+        id = "model",
+        short = 'm',
+        long = "model",
+        env = "RU_MODEL",
+        help = "Request unit model file used instead of the default one."
+    )]
+    model: Option<String>,
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+/// Whether ANALYZE is run before collecting the workload. The values of the
+/// former boolean flag are kept as aliases, so `DB_ANALYZE=true` still works.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum AnalyzeMode {
+    #[value(alias = "false", alias = "0", alias = "off")]
+    No,
+    #[value(alias = "true", alias = "1", alias = "on")]
+    Yes,
+    Prompt,
+}
+
+/// How the storage of the source databases is measured.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum StorageMeasurementMode {
+    Statistics,
+    Exact,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    #[command(about = "Calibrate the request unit model against an actual TiDB Serverless bill")]
+    Calibrate(CalibrationOptions),
+    #[command(about = "Compare two json or yaml reports and print the cost changes")]
+    Diff(DiffOptions),
+    #[command(about = "Print the trends of the runs recorded with --history")]
+    History(HistoryOptions),
+    #[command(about = "Print the JSON Schema of the json and yaml reports")]
+    Schema,
+    #[command(about = "Serve the estimation as a REST API")]
+    Serve(ServeOptions),
+}
+
+#[derive(Args)]
+struct ServeOptions {
+    #[arg(
+        id = "listen",
+        short = 'l',
+        long = "listen",
+        env = "LISTEN",
+        default_value = "127.0.0.1:8080",
+        help = "Address the API listens on",
+        num_args(1)
+    )]
+    listen: String,
+    #[arg(
+        id = "concurrency",
+        short = 'c',
+        long = "concurrency",
+        default_value = "4",
+        help = "Number of jobs collecting workloads at the same time, at least 1",
+        num_args(1)
+    )]
+    concurrency: NonZeroUsize,
+    #[arg(
+        id = "max-jobs",
+        long = "max-jobs",
+        default_value = "100",
+        help = "Number of unfinished jobs accepted before new ones are rejected, at least 1",
+        num_args(1)
+    )]
+    max_jobs: NonZeroUsize,
+    #[arg(
+        id = "token",
+        long = "token",
+        env = "SERVE_TOKEN",
+        help = "Bearer token every request must carry, prefer the environment variable to keep it out of the process list",
+        num_args(1),
+        required(true)
+    )]
+    token: String,
+    #[arg(
+        id = "allowed-hosts",
+        long = "allowed-hosts",
+        env = "ALLOWED_HOSTS",
+        help = "Hosts the submitted sources may connect to, where * matches any characters, e.g. *.db.internal; without them only snapshots are accepted",
+        num_args(1),
+        value_delimiter = ',',
+        action = ArgAction::Append
+    )]
+    allowed_hosts: Vec<String>,
+    #[arg(
+        id = "model",
+        short = 'm',
+        long = "model",
+        env = "RU_MODEL",
+        help = "Request unit model file used instead of the default one",
+        num_args(1)
+    )]
+    model: Option<String>,
+}
+
+#[derive(Args)]
+struct HistoryOptions {
+    #[arg(
+        id = "history",
+        long = "history",
+        env = "HISTORY",
+        help = "SQLite file the runs are recorded in",
+        num_args(1),
+        required(true)
+    )]
+    history: String,
+    #[arg(
+        id = "cluster",
+        short = 'c',
+        long = "cluster",
+        help = "Only print the runs of the cluster with this name",
+        num_args(1)
+    )]
+    cluster: Option<String>,
+    #[arg(
+        id = "limit",
+        short = 'l',
+        long = "limit",
+        default_value = "30",
+        help = "Number of latest runs printed for every cluster"
+    )]
+    limit: u32,
+}
+
+#[derive(Args)]
+struct DiffOptions {
+    #[arg(id = "old", help = "The earlier report, json or yaml")]
+    old: String,
+    #[arg(id = "new", help = "The later report, json or yaml")]
+    new: String,
+    #[arg(
+        id = "threshold",
+        short = 't',
+        long = "threshold",
+        help = "Exit with a non-zero code when the monthly cost of a cluster or of all clusters increases by more than this percentage",
+        num_args(1)
+    )]
+    threshold: Option<f64>,
+}
+
+#[derive(Args)]
+struct CalibrationOptions {
+    #[arg(
+        id = "snapshot",
+        short = 's',
+        long = "snapshot",
+        help = "JSON or YAML report collected from the source databases before the migration",
+        num_args(1..),
+        required(true)
+    )]
+    snapshots: Vec<String>,
+    #[arg(
+        id = "bill",
+        short = 'b',
+        long = "bill",
+        help = "CSV export of the TiDB Cloud bill with cluster, request units and row-based storage columns",
+        num_args(1),
+        required(true)
+    )]
+    bill: String,
+    #[arg(
+        id = "model",
+        short = 'm',
+        long = "model",
+        env = "RU_MODEL",
+        help = "Request unit model to start the calibration from instead of the default one",
+        num_args(1)
+    )]
+    model: Option<String>,
+    #[arg(
+        id = "name",
+        short = 'n',
+        long = "name",
+        help = "Name of the calibrated model",
+        num_args(1)
+    )]
+    name: Option<String>,
+    #[arg(
+        id = "model-version",
+        long = "model-version",
+        help = "Version of the calibrated model, defaults to the current date",
+        num_args(1)
+    )]
+    version: Option<String>,
+    #[arg(
+        id = "output",
+        short = 'o',
+        long = "output",
+        help = "File the calibrated model is written to, json or yaml",
+        num_args(1),
+        required(true)
+    )]
+    output: String,
+}
+
+fn load_model(output: OutputFormat, file: &Option<String>) -> RequestUnitModel {
+    match file {
+        Some(f) => match RequestUnitModel::load(f) {
+            Ok(r) => r,
+            Err(e) => output.fatal(
+                ExitCode::Failure,
+                "model_load_failure",
+                &format!("The request unit model failed to load: {}", e),
+            ),
+        },
+        None => RequestUnitModel::default(),
+    }
+}
+
+fn calibrate(options: CalibrationOptions) {
+    let output = OutputFormat::Human;
+    let base = load_model(output, &options.model);
+    match calibration::calibrate(
+        &output,
+        base,
+        &options.snapshots,
+        &options.bill,
+        options.name,
+        options.version,
+    )
+    .and_then(|model| model.save(&options.output))
+    {
+        Err(e) => output.fatal(
+            ExitCode::Failure,
+            "calibration_failure",
+            &format!("The calibration failed: {}", e),
+        ),
+        Ok(()) => output.info(&format!(
+            "The calibrated model is written to '{}'",
+            options.output
+        )),
+    }
+}
+
+fn diff(options: DiffOptions) {
+    let output = OutputFormat::Human;
+    let diff = match diff::diff(&options.old, &options.new) {
+        Ok(diff) => diff,
+        Err(e) => output.fatal(
+            ExitCode::Failure,
+            "diff_failure",
+            &format!("The reports failed to compare: {}", e),
+        ),
+    };
+    for warning in &diff.warnings {
+        output.warn("diff_incomparable", warning);
+    }
+    diff.print();
+    if let Some(threshold) = options.threshold {
+        let increases = diff.increases_beyond(threshold);
+        if !increases.is_empty() {
+            for increase in &increases {
+                output.error(ExitCode::CostIncrease.code(), increase);
+            }
+            exit(ExitCode::CostIncrease as i32);
+        }
+    }
+}
+
+async fn history(options: HistoryOptions) {
+    let output = OutputFormat::Human;
+    match history::load(&options.history, options.cluster.as_deref(), options.limit).await {
+        Err(e) => output.fatal(
+            ExitCode::Failure,
+            "history_failure",
+            &format!("The history failed to load: {}", e),
+        ),
+        Ok(runs) if runs.is_empty() => output.info("No runs are recorded in the history yet."),
+        Ok(runs) => history::print(&runs),
+    }
+}
+
+async fn serve(options: ServeOptions) {
+    let output = OutputFormat::Human;
+    let model = load_model(output, &options.model);
+    output.info(&format!("Serving the estimation API on {}", options.listen));
+    if let Err(e) = serve::serve(
+        &options.listen,
+        options.concurrency,
+        options.max_jobs,
+        options.token,
+        &options.allowed_hosts,
+        model,
+    )
+    .await
+    {
+        output.fatal(
+            ExitCode::Failure,
+            "serve_failure",
+            &format!("The API failed to serve: {}", e),
+        );
+    }
 }
+
 #[tokio::main]
 async fn main() {
     let options = CalculatorOptions::parse();
     let output = options.output;
+    if let Some(command) = options.command {
+        return match command {
+            Command::Calibrate(options) => calibrate(options),
+            Command::Diff(options) => diff(options),
+            Command::History(options) => history(options).await,
+            Command::Schema => println!("{}", Report::schema()),
+            Command::Serve(options) => serve(options).await,
+        };
+    }
 
-    output.welcome(&options);
-    let configurations = match options.batch {
-        Some(f) => match WorkloadSourceConfiguration::load(f) {
-            Ok(r) => r,
-            Err(e) => {
-                output.fatal(&e.to_string());
-                return;
-            }
+    if let Err(e) = calculator::check_region(&options.region) {
+        output.fatal(
+            ExitCode::InvalidRegion,
+            ExitCode::InvalidRegion.code(),
+            &e.to_string(),
+        );
+    }
+
+    let model = load_model(output, &options.model);
+    let scenarios = match &options.scenarios {
+        Some(file) => match Scenario::load(file) {
+            Ok(scenarios) => scenarios,
+            Err(e) => output.fatal(
+                ExitCode::Failure,
+                "scenario_load_failure",
+                &format!("The scenarios failed to load: {}", e),
+            ),
         },
-        None => vec![WorkloadSourceConfiguration::new(
-            options.host,
-            options.port,
-            options.user,
-            options.password,
-            options.database,
-        )],
+        None => Vec::new(),
     };
 
-    let mut workloads = Vec::with_capacity(configurations.len());
-    for configuration in configurations {
-        workloads.push(
-            match source::load_workload_description(
-                output,
-                configuration,
-                options.analyze,
-            )
+    let total;
+    let mut failures = Vec::new();
+    let mut workloads;
+    if let Some(file) = &options.workload {
+        workloads = match source::load_workload_specifications(&model, file) {
+            Ok(workloads) => workloads,
+            Err(e) => output.fatal(
+                ExitCode::Failure,
+                "workload_specification_failure",
+                &format!("The workload specification failed to load: {}", e),
+            ),
+        };
+        total = workloads.len();
+    } else {
+        output.welcome(
+            &options.host,
+            options.port,
+            &options.user,
+            options.database.as_deref().unwrap_or_default(),
+            &options.shards,
+        );
+        let configurations = match options.batch {
+            Some(f) => match WorkloadSourceConfiguration::load(f) {
+                Ok(r) => r,
+                Err(e) => output.fatal(ExitCode::Failure, "batch_load_failure", &e.to_string()),
+            },
+            None => vec![WorkloadSourceConfiguration {
+                replicas: options.replicas,
+                shards: options.shards,
+                ..WorkloadSourceConfiguration::new(
+                    options.host,
+                    options.port,
+                    options.user,
+                    options.password,
+                    options.database.unwrap_or_default(),
+                )
+            }],
+        };
+
+        let analyze_options = AnalyzeOptions {
+            max_table_size_in_bytes: options
+                .analyze_max_table_size
+                .map(|size| (size * 1024f64 * 1024f64 * 1024f64) as u64),
+            pause: Duration::from_secs_f64(options.analyze_pause.max(0f64)),
+            dry_run: options.analyze_dry_run,
+        };
+        let storage = StorageOptions {
+            mode: match options.storage_measurement {
+                StorageMeasurementMode::Statistics => StorageMode::Statistics,
+                StorageMeasurementMode::Exact => StorageMode::Exact {
+                    row_count_limit: options.row_count_limit,
+                },
+            },
+            convert: options.storage_conversion,
+        };
+        total = configurations.len();
+        workloads = Vec::with_capacity(total);
+        for configuration in configurations {
+            output.set_source(Some(configuration.name()));
+            let analyze = match options.analyze {
+                AnalyzeMode::No => false,
+                AnalyzeMode::Prompt if !analyze_options.dry_run => output
+                    .confirm("analyze_confirmation", "Running ANALYZE on the production system may affect ongoing queries. Do you want to proceed? (yes/no): ")
+                    .unwrap_or(false),
+                _ => true,
+            };
+            let load_options = LoadOptions {
+                analyze: analyze.then(|| analyze_options.clone()),
+                storage,
+                serverless: options.serverless,
+                compatibility: options.compatibility,
+            };
+            match source::load_workload_description(&output, &model, configuration, &load_options)
                 .await
             {
                 Err(e) => {
-                    return output.fatal(&format!("The workload failed to load: {}", e));
+                    let exit_code = ExitCode::of(&e);
+                    output.error(
+                        exit_code.code(),
+                        &format!("The workload failed to load: {:#}", e),
+                    );
+                    failures.push(exit_code);
                 }
-                Ok(Some(workload)) => workload,
+                Ok(Some(workload)) => workloads.push(workload),
                 Ok(None) => {
-                    return output.info("You are already using TiDB Serverless. Please check your billing in the TiDB Cloud Console for charges. For more information, visit https://docs.pingcap.com/tidbcloud/tidb-cloud-billing");
+                    output.info("You are already using TiDB Serverless. Please check your billing in the TiDB Cloud Console for charges, or run with --serverless to forecast them from the statement summary. For more information, visit https://docs.pingcap.com/tidbcloud/tidb-cloud-billing");
                 }
             }
-        );
+        }
+        output.set_source(None);
+    }
+    if workloads.is_empty() {
+        if let Some(exit_code) = failures.last() {
+            exit(*exit_code as i32);
+        }
+        return;
     }
 
-    match calculator::estimate(&options.region, &workloads) {
-        Err(e) => {
-            return output.fatal(&format!("The cost estimation failed: {}", e));
+    if options.interactive {
+        if let Err(e) = tui::run(&options.region, &model, workloads) {
+            output.fatal(
+                ExitCode::Failure,
+                "interactive_failure",
+                &format!("The interactive terminal UI failed: {}", e),
+            );
         }
+        return;
+    }
+
+    match calculator::estimate(&options.region, &model, &workloads) {
+        Err(e) => output.fatal(
+            ExitCode::of(&e),
+            "estimation_failure",
+            &format!("The cost estimation failed: {}", e),
+        ),
         Ok(estimations) => {
-            output.report(workloads, estimations);
+            if let Some(file) = &options.history {
+                if let Err(e) =
+                    history::record(file, &options.region, &workloads, &estimations).await
+                {
+                    output.warn(
+                        "history_failure",
+                        &format!("The run failed to record in the history: {}", e),
+                    );
+                }
+            }
+            let scenarios =
+                match scenario::evaluate(&options.region, &model, &scenarios, &workloads) {
+                    Ok(scenarios) => scenarios,
+                    Err(e) => output.fatal(
+                        ExitCode::of(&e),
+                        "scenario_failure",
+                        &format!("The scenario estimation failed: {}", e),
+                    ),
+                };
+            let mut report = Report::new(
+                &options.region,
+                &model,
+                workloads,
+                estimations,
+                options.explain,
+            );
+            for (cluster, scenarios) in report.clusters.iter_mut().zip(scenarios) {
+                cluster.scenarios = scenarios;
+            }
+            output.report(&report, options.output_file.as_deref());
+            if !failures.is_empty() {
+                output.fatal(
+                    ExitCode::PartialBatch,
+                    ExitCode::PartialBatch.code(),
+                    &format!(
+                        "{} of {} workload(s) failed to load and are missing from the report",
+                        failures.len(),
+                        total
+                    ),
+                );
+            }
         }
     }
 }
@@ -0,0 +1,170 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::config;
+
+/// Factors converting the collected workload into TiDB Serverless request units.
+/// The default model mirrors the published pricing rules, calibrated models can
+/// be loaded from a json or yaml file to better match the actual bills.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RequestUnitModel {
+    pub name: String,
+    pub version: String,
+    #[serde(default = "default_read_request_units_per_request")]
+    pub read_request_units_per_request: f64,
+    #[serde(default = "default_read_request_units_per_kib")]
+    pub read_request_units_per_kib: f64,
+    #[serde(default = "default_write_request_units_per_request")]
+    pub write_request_units_per_request: f64,
+    #[serde(default = "default_write_request_units_per_kib")]
+    pub write_request_units_per_kib: f64,
+    #[serde(default = "default_region_size_in_bytes")]
+    pub region_size_in_bytes: u64,
+    #[serde(default = "default_storage_factor")]
+    pub storage_factor: f64,
+}
+
+fn default_read_request_units_per_request() -> f64 {
+    1f64 / 8f64
+}
+
+fn default_read_request_units_per_kib() -> f64 {
+    1f64 / 64f64
+}
+
+fn default_write_request_units_per_request() -> f64 {
+    3f64
+}
+
+fn default_write_request_units_per_kib() -> f64 {
+    3f64
+}
+
+fn default_region_size_in_bytes() -> u64 {
+    256 * 1024 * 1024
+}
+
+fn default_storage_factor() -> f64 {
+    1f64
+}
+
+impl Default for RequestUnitModel {
+    fn default() -> Self {
+        Self {
+            name: "default".into(),
+            version: "1".into(),
+            read_request_units_per_request: default_read_request_units_per_request(),
+            read_request_units_per_kib: default_read_request_units_per_kib(),
+            write_request_units_per_request: default_write_request_units_per_request(),
+            write_request_units_per_kib: default_write_request_units_per_kib(),
+            region_size_in_bytes: default_region_size_in_bytes(),
+            storage_factor: default_storage_factor(),
+        }
+    }
+}
+
+impl RequestUnitModel {
+    pub fn load(file: &str) -> Result<Self> {
+        let model: Self = config::load(file, "request unit model")?;
+        model.validate()?;
+        Ok(model)
+    }
+
+    /// Rejects the factors the estimation cannot work with. A request unit
+    /// factor may be zero, as calibration pins the factors the bill does not
+    /// support at zero, the region size and storage factor must be positive.
+    pub fn validate(&self) -> Result<()> {
+        for (name, factor) in [
+            (
+                "read_request_units_per_request",
+                self.read_request_units_per_request,
+            ),
+            (
+                "read_request_units_per_kib",
+                self.read_request_units_per_kib,
+            ),
+            (
+                "write_request_units_per_request",
+                self.write_request_units_per_request,
+            ),
+            (
+                "write_request_units_per_kib",
+                self.write_request_units_per_kib,
+            ),
+        ] {
+            if !factor.is_finite() || factor < 0f64 {
+                return Err(anyhow!(
+                    "The factor '{}' of the request unit model must be a finite, non-negative number, not {}",
+                    name,
+                    factor
+                ));
+            }
+        }
+        if !self.storage_factor.is_finite() || self.storage_factor <= 0f64 {
+            return Err(anyhow!(
+                "The factor 'storage_factor' of the request unit model must be a finite, positive number, not {}",
+                self.storage_factor
+            ));
+        }
+        if self.region_size_in_bytes == 0 {
+            return Err(anyhow!(
+                "The 'region_size_in_bytes' of the request unit model must be positive"
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn read_request_units(&self, requests: f64, bytes: f64) -> f64 {
+        requests * self.read_request_units_per_request
+            + bytes / 1024f64 * self.read_request_units_per_kib
+    }
+
+    pub fn write_request_units(&self, requests: f64, bytes: f64) -> f64 {
+        requests * self.write_request_units_per_request
+            + bytes / 1024f64 * self.write_request_units_per_kib
+    }
+
+    pub fn save(&self, file: &str) -> Result<()> {
+        config::save(file, self, "request unit model")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validate_rejects_unusable_models() {
+        assert!(RequestUnitModel::default().validate().is_ok());
+        for model in [
+            RequestUnitModel {
+                region_size_in_bytes: 0,
+                ..Default::default()
+            },
+            RequestUnitModel {
+                storage_factor: 0f64,
+                ..Default::default()
+            },
+            RequestUnitModel {
+                read_request_units_per_kib: -1f64,
+                ..Default::default()
+            },
+            RequestUnitModel {
+                write_request_units_per_request: f64::NAN,
+                ..Default::default()
+            },
+            RequestUnitModel {
+                write_request_units_per_kib: f64::INFINITY,
+                ..Default::default()
+            },
+        ] {
+            assert!(model.validate().is_err(), "{:?}", model);
+        }
+        assert!(RequestUnitModel {
+            read_request_units_per_kib: 0f64,
+            ..Default::default()
+        }
+        .validate()
+        .is_ok());
+    }
+}
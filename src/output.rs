@@ -1,16 +1,110 @@
-use crate::calculator::WorkloadEstimation;
-use crate::source::WorkloadDescription;
-use crate::CalculatorOptions;
+use crate::calculator::InvalidRegion;
+use crate::compatibility::{CompatibilityIssue, Severity};
+use crate::report::{ClusterReport, CostReport, Report, WorkloadInput};
+use crate::source::{Diagnostics, ShardContribution, SourceError, StorageMeasurement};
+use anyhow::Result;
 use colored::Colorize;
 use prettytable::{row, Table};
-use readable::num::Float;
+use readable::num::{Float, Unsigned};
 use serde::Serialize;
+use std::cmp::max;
+use std::fmt::Write as _;
+use std::fs;
+use std::io::{self, Write};
 use std::process::exit;
+use std::sync::Mutex;
 
-#[derive(Serialize)]
-struct WorkloadReport {
-    workload: WorkloadDescription,
-    estimation: WorkloadEstimation,
+mod html;
+mod openmetrics;
+
+const TOP_DIGESTS: usize = 10;
+const TOP_TABLES: usize = 20;
+const COMPATIBILITY_OBJECTS: usize = 3;
+const BASELINE: &str = "baseline";
+const NOTES: [&str; 7] = [
+    "Request units are estimated based on statistical data from the past, up to seven days. Be cautious: severe fluctuations in recent workload, such as ingesting a large volume of data, can skew the final estimation.",
+    "The low and high columns apply optimistic and pessimistic assumptions on row size, region size and storage statistics to bracket the expected estimation.",
+    "The storage size is estimated from statistical data, which differs from the actual data size.",
+    "TiDB Serverless encodes data differently from MySQL, resulting in slightly different storage consumption.",
+    "The TiDB Serverless storage size meter does not account for data compression or replicas.",
+    "For detailed pricing information, visit https://www.pingcap.com/tidb-serverless-pricing-details",
+    "For additional questions, refer to the FAQs on https://docs.pingcap.com/tidbcloud/serverless-faqs",
+];
+
+#[derive(Clone, Copy, Debug, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Level {
+    Error,
+    Warning,
+}
+
+impl Level {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Level::Error => "error",
+            Level::Warning => "warning",
+        }
+    }
+}
+
+/// An error or warning reported while collecting the workload, kept for
+/// formats rendering them along with the estimation. In machine formats it is
+/// also written to stderr as a structured log event.
+#[derive(Clone, Debug, Serialize)]
+struct Diagnostic {
+    level: Level,
+    source: String,
+    code: String,
+    message: String,
+}
+
+static DIAGNOSTICS: Mutex<Vec<Diagnostic>> = Mutex::new(Vec::new());
+/// The workload source being collected, attributed to the diagnostics.
+static SOURCE: Mutex<Option<String>> = Mutex::new(None);
+
+/// The process exit codes, telling apart the failures scripts may act on.
+#[derive(Clone, Copy, Debug)]
+pub enum ExitCode {
+    Failure = 1,
+    Connection = 2,
+    UnsupportedSource = 3,
+    InvalidRegion = 4,
+    PartialBatch = 5,
+    CostIncrease = 6,
+}
+
+impl ExitCode {
+    pub fn of(error: &anyhow::Error) -> Self {
+        if let Some(error) = error.downcast_ref::<SourceError>() {
+            match error {
+                SourceError::Connection(_) => ExitCode::Connection,
+                SourceError::Unsupported(_) => ExitCode::UnsupportedSource,
+            }
+        } else if error.is::<InvalidRegion>() {
+            ExitCode::InvalidRegion
+        } else {
+            ExitCode::Failure
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            ExitCode::Failure => "failure",
+            ExitCode::Connection => "connection_failure",
+            ExitCode::UnsupportedSource => "unsupported_source",
+            ExitCode::InvalidRegion => "invalid_region",
+            ExitCode::PartialBatch => "partial_batch_failure",
+            ExitCode::CostIncrease => "cost_increase",
+        }
+    }
+}
+
+/// The cost of a SKU with its low, expected and high estimation.
+struct SkuCost {
+    sku: &'static str,
+    low: f64,
+    expected: f64,
+    high: f64,
 }
 
 #[derive(clap::ValueEnum, Clone, Copy, Default, Debug, Serialize)]
@@ -19,34 +113,97 @@ pub enum OutputFormat {
     Human,
     Json,
     Yaml,
+    Csv,
+    Markdown,
+    Html,
+    #[value(name = "openmetrics")]
+    OpenMetrics,
+}
+
+impl Diagnostics for OutputFormat {
+    fn error(&self, code: &str, message: &str) {
+        OutputFormat::error(self, code, message);
+    }
+
+    fn warn(&self, code: &str, message: &str) {
+        OutputFormat::warn(self, code, message);
+    }
 }
 
 impl OutputFormat {
-    pub fn welcome(&self, options: &CalculatorOptions) {
-        match &self {
-            OutputFormat::Json => return,
-            OutputFormat::Yaml => return,
-            _ => (),
+    pub fn welcome(&self, host: &str, port: u16, user: &str, database: &str, shards: &[String]) {
+        if !matches!(self, OutputFormat::Human) {
+            return;
         }
+        let host = if shards.is_empty() {
+            format!("{}:{}", host, port)
+        } else {
+            shards.join(",")
+        };
         println!(
             "Connecting to the MySQL compatible database at '{}' as the user '{}' using the database '{}'",
-            format!("{}:{}", options.host, options.port).bold().green(),
-            options.user.bold().green(),
-            options.database.bold().green(),
+            host.bold().green(),
+            user.bold().green(),
+            database.bold().green(),
         );
     }
 
-    pub fn fatal(&self, error: &str) {
-        self.error(error);
-        exit(1);
+    /// Formats read by other programs, whose diagnostics are written as JSON
+    /// lines instead of colored text.
+    fn is_machine_readable(&self) -> bool {
+        matches!(
+            self,
+            OutputFormat::Json | OutputFormat::Yaml | OutputFormat::Csv | OutputFormat::OpenMetrics
+        )
+    }
+
+    pub fn set_source(&self, source: Option<String>) {
+        if let Ok(mut current) = SOURCE.lock() {
+            *current = source;
+        }
+    }
+
+    /// Reports the error and exits, flushing the console first so the
+    /// diagnostics and any partial output are not lost.
+    pub fn fatal(&self, exit_code: ExitCode, code: &str, error: &str) -> ! {
+        self.error(code, error);
+        io::stdout().flush().unwrap_or(());
+        io::stderr().flush().unwrap_or(());
+        exit(exit_code as i32);
+    }
+
+    fn diagnose(&self, level: Level, code: &str, message: &str) {
+        let diagnostic = Diagnostic {
+            level,
+            source: SOURCE
+                .lock()
+                .ok()
+                .and_then(|source| source.clone())
+                .unwrap_or_else(|| env!("CARGO_PKG_NAME").into()),
+            code: code.into(),
+            message: message.into(),
+        };
+        if self.is_machine_readable() {
+            if let Ok(event) = serde_json::to_string(&diagnostic) {
+                eprintln!("{}", event);
+            }
+        } else {
+            match level {
+                Level::Error => eprintln!("{}", message.bold().red()),
+                Level::Warning => eprintln!("{}", message.bold().yellow()),
+            }
+        }
+        if let Ok(mut diagnostics) = DIAGNOSTICS.lock() {
+            diagnostics.push(diagnostic);
+        }
     }
 
-    pub fn error(&self, error: &str) {
-        println!("{}", error.bold().red());
+    pub fn error(&self, code: &str, error: &str) {
+        self.diagnose(Level::Error, code, error);
     }
 
-    pub fn warn(&self, warn: &str) {
-        println!("{}", warn.bold().yellow());
+    pub fn warn(&self, code: &str, warn: &str) {
+        self.diagnose(Level::Warning, code, warn);
     }
 
     pub fn info(&self, info: &str) {
@@ -55,70 +212,552 @@ impl OutputFormat {
         }
     }
 
-    pub fn report(&self, workloads: Vec<WorkloadDescription>, estimation: Vec<WorkloadEstimation>) {
+    /// Asks a yes or no question on the console until it is answered, the end
+    /// of the input answers no.
+    pub fn confirm(&self, code: &str, question: &str) -> Result<bool> {
+        loop {
+            self.warn(code, question);
+            io::stderr().flush().unwrap_or(());
+            let mut confirmation = String::new();
+            if io::stdin().read_line(&mut confirmation)? == 0 {
+                return Ok(false);
+            }
+            match confirmation.trim().to_lowercase().as_str() {
+                "yes" => return Ok(true),
+                "no" => return Ok(false),
+                _ => continue,
+            }
+        }
+    }
+
+    pub fn report(&self, report: &Report, output_file: Option<&str>) {
         if let OutputFormat::Human = *self {
-            return Self::output_human(estimation);
-        }
-        let reports: Vec<WorkloadReport> = workloads
-            .into_iter()
-            .zip(estimation)
-            .map(|pair| WorkloadReport {
-                workload: pair.0,
-                estimation: pair.1,
+            if output_file.is_some() {
+                self.warn("human_output_file", "The human output format is printed to the console, use another format to write the report to a file.");
+            }
+            return Self::output_human(&report.clusters);
+        }
+        let content = match self.render(report) {
+            Ok(content) => content,
+            Err(e) => self.fatal(
+                ExitCode::Failure,
+                "render_failed",
+                &format!("The report failed to render: {}", e),
+            ),
+        };
+        match output_file {
+            Some(file) => {
+                if let Err(e) = Self::write_atomically(file, &content) {
+                    self.fatal(
+                        ExitCode::Failure,
+                        "write_failed",
+                        &format!("The report failed to write to '{}': {}", file, e),
+                    );
+                }
+            }
+            None => print!("{}", content),
+        }
+    }
+
+    fn render(&self, report: &Report) -> Result<String> {
+        Ok(match *self {
+            OutputFormat::Human => unreachable!(),
+            OutputFormat::Json => format!("{}\n", serde_json::to_string_pretty(report)?),
+            OutputFormat::Yaml => serde_yaml::to_string(report)?,
+            OutputFormat::Csv => Self::render_csv(&report.clusters)?,
+            OutputFormat::Markdown => Self::render_markdown(&report.clusters),
+            OutputFormat::Html => format!(
+                "{}\n",
+                html::render(
+                    report,
+                    &DIAGNOSTICS
+                        .lock()
+                        .map(|diagnostics| diagnostics.clone())
+                        .unwrap_or_default()
+                )
+            ),
+            OutputFormat::OpenMetrics => openmetrics::render(report),
+        })
+    }
+
+    /// Writes the report next to its destination and renames it into place, so
+    /// readers such as the node exporter textfile collector never see a partial file.
+    fn write_atomically(file: &str, content: &str) -> std::io::Result<()> {
+        let temporary = format!("{}.{}.tmp", file, std::process::id());
+        fs::write(&temporary, content)?;
+        fs::rename(&temporary, file).inspect_err(|_| {
+            let _ = fs::remove_file(&temporary);
+        })
+    }
+
+    fn gibibytes(bytes: u64) -> String {
+        format!("{:.2} GiB", bytes as f64 / (1024f64 * 1024f64 * 1024f64))
+    }
+
+    fn dollars(cost: f64) -> String {
+        if cost < 0f64 {
+            format!("-${}", Float::from_2(-cost))
+        } else {
+            format!("${}", Float::from_2(cost))
+        }
+    }
+
+    fn sku_costs(costs: &CostReport) -> [SkuCost; 4] {
+        [
+            SkuCost {
+                sku: "Request Units",
+                low: costs.request_units.low,
+                expected: costs.request_units.expected,
+                high: costs.request_units.high,
+            },
+            SkuCost {
+                sku: "Row-based Storage",
+                low: costs.row_based_storage.low,
+                expected: costs.row_based_storage.expected,
+                high: costs.row_based_storage.high,
+            },
+            SkuCost {
+                sku: "Free Credits",
+                low: -costs.free_credit,
+                expected: -costs.free_credit,
+                high: -costs.free_credit,
+            },
+            SkuCost {
+                sku: "Total",
+                low: costs.total.low,
+                expected: costs.total.expected,
+                high: costs.total.high,
+            },
+        ]
+    }
+
+    fn workload_inputs(workload: &WorkloadInput) -> [(&'static str, u64); 7] {
+        [
+            ("read_requests_per_hour", workload.read_requests_per_hour),
+            ("read_bytes_per_hour", workload.read_bytes_per_hour),
+            ("write_requests_per_hour", workload.write_requests_per_hour),
+            ("write_bytes_per_hour", workload.write_bytes_per_hour),
+            ("egress_bytes_per_hour", workload.egress_bytes_per_hour),
+            ("storage_data_in_bytes", workload.storage_data_in_bytes),
+            ("storage_index_in_bytes", workload.storage_index_in_bytes),
+        ]
+    }
+
+    /// The collected workload followed by its what-if scenarios.
+    fn scenarios(report: &ClusterReport) -> Vec<(&str, &WorkloadInput, &CostReport)> {
+        let mut scenarios = vec![(BASELINE, &report.workload, &report.costs)];
+        scenarios.extend(
+            report
+                .scenarios
+                .iter()
+                .map(|scenario| (scenario.name.as_str(), &scenario.workload, &scenario.costs)),
+        );
+        scenarios
+    }
+
+    fn change(baseline: f64, cost: f64) -> String {
+        if baseline == 0f64 {
+            return if cost == 0f64 { "+0.0%" } else { "n/a" }.into();
+        }
+        format!("{:+.1}%", (cost - baseline) * 100f64 / baseline.abs())
+    }
+
+    /// The number of objects raising an issue followed by the first ones.
+    fn compatibility_objects(issue: &CompatibilityIssue) -> String {
+        let mut objects = issue
+            .objects
+            .iter()
+            .take(COMPATIBILITY_OBJECTS)
+            .cloned()
+            .collect::<Vec<_>>()
+            .join(", ");
+        if issue.objects.len() > COMPATIBILITY_OBJECTS {
+            objects.push_str(", ...");
+        }
+        format!("{} ({})", issue.objects.len(), objects)
+    }
+
+    /// The number of issues of a severity, none when the schema was not scanned.
+    fn compatibility_count(report: &ClusterReport, severity: Severity) -> Option<usize> {
+        report.compatibility.as_ref().map(|issues| {
+            issues
+                .iter()
+                .filter(|issue| issue.severity == severity)
+                .count()
+        })
+    }
+
+    /// The issues found by a scan of the schema, none when there are none.
+    fn compatibility_issues(report: &ClusterReport) -> Option<&[CompatibilityIssue]> {
+        report
+            .compatibility
+            .as_deref()
+            .filter(|issues| !issues.is_empty())
+    }
+
+    /// The shards of a group with their share of the request units and of
+    /// the storage, in percent.
+    fn shard_shares(report: &ClusterReport) -> Vec<(&ShardContribution, f64, f64)> {
+        let request_units = max(
+            report
+                .shards
+                .iter()
+                .map(|shard| shard.request_units_per_hour)
+                .sum(),
+            1,
+        );
+        let storage = max(
+            report
+                .shards
+                .iter()
+                .map(|shard| shard.storage_in_bytes)
+                .sum(),
+            1,
+        );
+        report
+            .shards
+            .iter()
+            .map(|shard| {
+                (
+                    shard,
+                    shard.request_units_per_hour as f64 * 100f64 / request_units as f64,
+                    shard.storage_in_bytes as f64 * 100f64 / storage as f64,
+                )
             })
-            .collect();
+            .collect()
+    }
 
-        println!(
-            "{}",
-            match *self {
-                OutputFormat::Json => serde_json::to_string_pretty(&reports).unwrap(),
-                OutputFormat::Yaml => serde_yaml::to_string(&reports).unwrap(),
-                _ => unreachable!(),
+    fn render_csv(reports: &[ClusterReport]) -> Result<String> {
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        let mut header = vec!["cluster", "scenario"];
+        header.extend(Self::workload_inputs(&Default::default()).map(|input| input.0));
+        header.extend(["sku", "low", "expected", "high"]);
+        header.extend(["compatibility_blockers", "compatibility_warnings"]);
+        writer.write_record(header)?;
+        for report in reports {
+            for (scenario, workload, costs) in Self::scenarios(report) {
+                let inputs = Self::workload_inputs(workload).map(|input| input.1.to_string());
+                for cost in Self::sku_costs(costs) {
+                    let mut record = vec![report.name.clone(), scenario.to_string()];
+                    record.extend(inputs.iter().cloned());
+                    record.extend([
+                        cost.sku.to_string(),
+                        format!("{:.2}", cost.low),
+                        format!("{:.2}", cost.expected),
+                        format!("{:.2}", cost.high),
+                        Self::compatibility_count(report, Severity::Blocker)
+                            .map_or(String::new(), |count| count.to_string()),
+                        Self::compatibility_count(report, Severity::Warning)
+                            .map_or(String::new(), |count| count.to_string()),
+                    ]);
+                    writer.write_record(record)?;
+                }
             }
-        );
+        }
+        Ok(String::from_utf8(writer.into_inner()?)?)
+    }
+
+    fn markdown_escape(text: &str) -> String {
+        text.replace('|', "\\|").replace('\n', " ")
+    }
+
+    /// A code span fenced by more backticks than the text holds in a row,
+    /// padded so quoted identifiers at either end do not join the fence.
+    fn markdown_code(text: &str) -> String {
+        let longest = text.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+        let fence = "`".repeat(longest + 1);
+        format!("{} {} {}", fence, Self::markdown_escape(text), fence)
+    }
+
+    fn render_markdown(reports: &[ClusterReport]) -> String {
+        let mut markdown = String::new();
+        let _ = writeln!(markdown, "# TiDB Serverless Cost Estimation");
+        for report in reports {
+            let total = &report.costs.total;
+            let _ = writeln!(markdown, "\n## {}\n", Self::markdown_escape(&report.name));
+            let _ = writeln!(
+                markdown,
+                "The estimated monthly cost for the workload is **{}**, ranging from {} to {}.\n",
+                Self::dollars(total.expected),
+                Self::dollars(total.low),
+                Self::dollars(total.high)
+            );
+            if let Some(provider) = report.provider {
+                let _ = writeln!(markdown, "The source runs on {}.\n", provider);
+            }
+            let _ = writeln!(markdown, "| Workload input | Value |");
+            let _ = writeln!(markdown, "| --- | ---: |");
+            for (input, value) in Self::workload_inputs(&report.workload) {
+                let _ = writeln!(markdown, "| {} | {} |", input, value);
+            }
+            let _ = writeln!(markdown, "\n| SKU | Low | Expected | High |");
+            let _ = writeln!(markdown, "| --- | ---: | ---: | ---: |");
+            for cost in Self::sku_costs(&report.costs) {
+                let _ = writeln!(
+                    markdown,
+                    "| {} | {} | {} | {} |",
+                    cost.sku,
+                    Self::dollars(cost.low),
+                    Self::dollars(cost.expected),
+                    Self::dollars(cost.high)
+                );
+            }
+            if let Some(issues) = Self::compatibility_issues(report) {
+                let _ = writeln!(markdown, "\n### Compatibility\n");
+                let _ = writeln!(markdown, "| Severity | Code | Objects | Message |");
+                let _ = writeln!(markdown, "| --- | --- | --- | --- |");
+                for issue in issues {
+                    let _ = writeln!(
+                        markdown,
+                        "| {} | `{}` | {} | {} |",
+                        issue.severity.as_str(),
+                        issue.code,
+                        Self::markdown_escape(&Self::compatibility_objects(issue)),
+                        Self::markdown_escape(&issue.message)
+                    );
+                }
+            }
+            if !report.shards.is_empty() {
+                let _ = writeln!(markdown, "\n### Shards\n");
+                let _ = writeln!(
+                    markdown,
+                    "| Shard | RU/h | RU share | Storage | Storage share |"
+                );
+                let _ = writeln!(markdown, "| --- | ---: | ---: | ---: | ---: |");
+                for (shard, request_units_share, storage_share) in Self::shard_shares(report) {
+                    let _ = writeln!(
+                        markdown,
+                        "| {} | {} | {:.1}% | {} | {:.1}% |",
+                        Self::markdown_escape(&shard.name),
+                        shard.request_units_per_hour,
+                        request_units_share,
+                        Self::gibibytes(shard.storage_in_bytes),
+                        storage_share
+                    );
+                }
+            }
+            if !report.scenarios.is_empty() {
+                let _ = writeln!(markdown, "\n### Scenarios\n");
+                let _ = writeln!(
+                    markdown,
+                    "| Scenario | Request Units | Row-based Storage | Total | Change |"
+                );
+                let _ = writeln!(markdown, "| --- | ---: | ---: | ---: | ---: |");
+                for (scenario, _, costs) in Self::scenarios(report) {
+                    let _ = writeln!(
+                        markdown,
+                        "| {} | {} | {} | {} | {} |",
+                        Self::markdown_escape(scenario),
+                        Self::dollars(costs.request_units.expected),
+                        Self::dollars(costs.row_based_storage.expected),
+                        Self::dollars(costs.total.expected),
+                        Self::change(report.costs.total.expected, costs.total.expected)
+                    );
+                }
+            }
+            if !report.digests.is_empty() {
+                let _ = writeln!(markdown, "\n### Top statements by request units\n");
+                let _ = writeln!(markdown, "| Digest | Statement | Executions/h | RU/h |");
+                let _ = writeln!(markdown, "| --- | --- | ---: | ---: |");
+                for digest in report.digests.iter().take(TOP_DIGESTS) {
+                    let _ = writeln!(
+                        markdown,
+                        "| `{}` | {} | {} | {} |",
+                        digest.digest,
+                        Self::markdown_code(&Self::truncate(&digest.sql, 120)),
+                        digest.executions_per_hour,
+                        digest.request_units_per_hour
+                    );
+                }
+            }
+        }
+        let _ = writeln!(markdown, "\n## Notes\n");
+        for note in NOTES {
+            let _ = writeln!(markdown, "- {}", note);
+        }
+        markdown
     }
 
-    fn output_human_step(index: Option<usize>, estimation: &WorkloadEstimation) {
+    fn output_human_step(index: Option<usize>, report: &ClusterReport) {
         if let Some(index) = index {
-            println!("Cluster: {}", format!("{}", index).bold().green());
+            println!(
+                "Cluster: {} {}",
+                format!("{}", index).bold().green(),
+                report.name.bold().green()
+            );
         }
-        let total =
-            if estimation.storage_cost + estimation.request_units_cost <= estimation.free_credit {
-                "$0.00".to_string()
-            } else {
-                format!(
-                    "${}",
-                    Float::from_2(
-                        estimation.storage_cost + estimation.request_units_cost
-                            - estimation.free_credit
-                    )
-                )
+        let total = &report.costs.total;
+        println!(
+            "The estimated monthly cost for your workload is {}, ranging from {} to {}",
+            Self::dollars(total.expected).bold().green(),
+            Self::dollars(total.low).bold().green(),
+            Self::dollars(total.high).bold().green(),
+        );
+        if let Some(provider) = report.provider {
+            println!("The source runs on {}", provider.to_string().bold().green());
+        }
+        if !matches!(
+            report.workload.storage_measurement,
+            StorageMeasurement::Statistics | StorageMeasurement::Specification
+        ) {
+            println!(
+                "The storage is measured from the {}",
+                report.workload.storage_measurement
+            );
+        }
+        if let Some(source_in_bytes) = report.workload.source_storage_in_bytes {
+            let tidb_in_bytes =
+                report.workload.storage_data_in_bytes + report.workload.storage_index_in_bytes;
+            println!(
+                "The MySQL storage of {} is estimated to take {} on TiDB Serverless",
+                Self::gibibytes(source_in_bytes).bold().green(),
+                Self::gibibytes(tidb_in_bytes).bold().green(),
+            );
+        }
+        let mut table = Table::new();
+        table.set_titles(row![bFg -> "SKU", bFgr -> "Low", bFgr -> "Expected", bFgr -> "High"]);
+        for cost in Self::sku_costs(&report.costs) {
+            table.add_row(row![bFg -> cost.sku,
+                bFgr -> Self::dollars(cost.low),
+                bFgr -> Self::dollars(cost.expected),
+                bFgr -> Self::dollars(cost.high)]);
+        }
+        table.printstd();
+        Self::output_human_compatibility(report);
+        Self::output_human_shards(report);
+        Self::output_human_scenarios(report);
+        Self::output_human_digests(report);
+        if let Some(explain) = &report.explain {
+            print!("{}", explain);
+        }
+    }
+
+    fn truncate(text: &str, width: usize) -> String {
+        if text.chars().count() <= width {
+            text.to_string()
+        } else {
+            format!("{}...", text.chars().take(width - 3).collect::<String>())
+        }
+    }
+
+    fn output_human_compatibility(report: &ClusterReport) {
+        let Some(issues) = Self::compatibility_issues(report) else {
+            return;
+        };
+        println!(
+            "Compatibility: {} blockers, {} warnings",
+            Self::compatibility_count(report, Severity::Blocker).unwrap_or(0),
+            Self::compatibility_count(report, Severity::Warning).unwrap_or(0)
+        );
+        let mut table = Table::new();
+        table
+            .set_titles(row![bFg -> "Severity", bFg -> "Code", bFg -> "Objects", bFg -> "Message"]);
+        for issue in issues {
+            let severity = match issue.severity {
+                Severity::Blocker => issue.severity.as_str().red(),
+                Severity::Warning => issue.severity.as_str().yellow(),
             };
+            table.add_row(row![severity,
+                Fg -> issue.code,
+                Fg -> Self::truncate(&Self::compatibility_objects(issue), 60),
+                Fg -> issue.message]);
+        }
+        table.printstd();
+    }
+
+    fn output_human_shards(report: &ClusterReport) {
+        if report.shards.is_empty() {
+            return;
+        }
+        println!("Shards");
+        let mut table = Table::new();
+        table.set_titles(row![bFg -> "Shard", bFgr -> "RU/h", bFgr -> "RU Share", bFgr -> "Storage", bFgr -> "Storage Share"]);
+        for (shard, request_units_share, storage_share) in Self::shard_shares(report) {
+            table.add_row(row![Fg -> shard.name,
+                Fgr -> Unsigned::from(shard.request_units_per_hour),
+                Fgr -> format!("{}%", Float::from_1(request_units_share)),
+                Fgr -> Self::gibibytes(shard.storage_in_bytes),
+                Fgr -> format!("{}%", Float::from_1(storage_share))]);
+        }
+        table.printstd();
+    }
+
+    fn output_human_scenarios(report: &ClusterReport) {
+        if report.scenarios.is_empty() {
+            return;
+        }
+        println!("What-if scenarios");
+        let mut table = Table::new();
+        table.set_titles(row![bFg -> "Scenario", bFgr -> "Request Units", bFgr -> "Row-based Storage", bFgr -> "Total", bFgr -> "Change"]);
+        for (scenario, _, costs) in Self::scenarios(report) {
+            table.add_row(row![bFg -> scenario,
+                bFgr -> Self::dollars(costs.request_units.expected),
+                bFgr -> Self::dollars(costs.row_based_storage.expected),
+                bFgr -> Self::dollars(costs.total.expected),
+                bFgr -> Self::change(report.costs.total.expected, costs.total.expected)]);
+        }
+        table.printstd();
+    }
+
+    fn output_human_digests(report: &ClusterReport) {
+        if report.digests.is_empty() {
+            return;
+        }
+        let total = max(
+            report
+                .digests
+                .iter()
+                .map(|digest| digest.request_units_per_hour)
+                .sum(),
+            1,
+        );
         println!(
-            "The estimated monthly cost for your workload is {}",
-            total.bold().green()
+            "Top statements by request units{}",
+            if report.workload.request_units_per_hour.is_some() {
+                ""
+            } else {
+                " (estimated)"
+            }
         );
         let mut table = Table::new();
-        table.set_titles(row![bFg -> "SKU", bFgr -> "Cost"]);
-        table.add_row(row![bFg -> "Request Units", bFgr -> format!("${}", Float::from_2(estimation.request_units_cost))]);
-        table.add_row(row![bFg -> "Row-based Storage", bFgr -> format!("${}", Float::from_2(estimation.storage_cost))]);
-        table.add_row(row![bFg -> "Free Credits", bFgr -> format!("-${}", Float::from_2(estimation.free_credit))]);
-        table.add_row(row![bFg -> "Total", bFgr -> total]);
+        table.set_titles(row![bFg -> "Digest", bFg -> "Statement", bFgr -> "Executions/h", bFgr -> "RU/h", bFgr -> "Share"]);
+        for digest in report.digests.iter().take(TOP_DIGESTS) {
+            table.add_row(row![Fg -> Self::truncate(&digest.digest, 16),
+                Fg -> Self::truncate(&digest.sql, 60),
+                Fgr -> Unsigned::from(digest.executions_per_hour),
+                Fgr -> Unsigned::from(digest.request_units_per_hour),
+                Fgr -> format!("{}%", Float::from_1(digest.request_units_per_hour as f64 * 100f64 / total as f64))]);
+        }
         table.printstd();
     }
 
-    fn output_human(estimation: Vec<WorkloadEstimation>) {
-        let single_workload = estimation.len() == 1;
-        for pair in estimation.iter().enumerate() {
+    fn output_human(reports: &[ClusterReport]) {
+        let single_workload = reports.len() == 1;
+        for pair in reports.iter().enumerate() {
             Self::output_human_step(if single_workload { None } else { Some(pair.0) }, pair.1)
         }
 
         println!("\n{}", "Notes:".bold().green());
-        println!("{}", "* Request units are estimated based on statistical data from the past, up to seven days. Be cautious: severe fluctuations in recent workload, such as ingesting a large volume of data, can skew the final estimation.".bold().green());
-        println!("{}", "* The storage size is estimated from statistical data, which differs from the actual data size.".bold().green());
-        println!("{}", "* TiDB Serverless encodes data differently from MySQL, resulting in slightly different storage consumption.".bold().green());
-        println!("{}", "* The TiDB Serverless storage size meter does not account for data compression or replicas.".bold().green());
-        println!("{}", "* For detailed pricing information, visit https://www.pingcap.com/tidb-serverless-pricing-details".bold().green());
-        println!("{}", "* For additional questions, refer to the FAQs on https://docs.pingcap.com/tidbcloud/serverless-faqs".bold().green());
+        for note in NOTES {
+            println!("{}", format!("* {}", note).bold().green());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn markdown_code_fences_quoted_identifiers() {
+        assert_eq!(
+            OutputFormat::markdown_code("SELECT `id` FROM `orders` WHERE `a` | `b`"),
+            "`` SELECT `id` FROM `orders` WHERE `a` \\| `b` ``"
+        );
+        assert_eq!(
+            OutputFormat::markdown_code("SELECT * FROM `a``b`"),
+            "``` SELECT * FROM `a``b` ```"
+        );
+        assert_eq!(OutputFormat::markdown_code("SELECT ?"), "` SELECT ? `");
     }
 }
@@ -0,0 +1,241 @@
+use std::f64::consts::PI;
+use std::fmt::Write;
+
+use super::{Diagnostic, OutputFormat, NOTES, TOP_DIGESTS, TOP_TABLES};
+use crate::report::{ClusterReport, Report};
+
+const STYLE: &str = "body{font-family:-apple-system,'Segoe UI',Helvetica,Arial,sans-serif;margin:2em auto;max-width:1100px;color:#1f2328;padding:0 1em}\
+h1{border-bottom:2px solid #e34c26;padding-bottom:.3em}\
+section{border:1px solid #d0d7de;border-radius:6px;padding:0 1.5em 1em;margin:1.5em 0}\
+table{border-collapse:collapse;margin:1em 0}\
+th,td{border:1px solid #d0d7de;padding:.35em .8em}\
+th{background:#f6f8fa;text-align:left}\
+td.number{text-align:right;font-variant-numeric:tabular-nums}\
+tr.total td{font-weight:bold}\
+code{font-size:.85em;word-break:break-all}\
+.breakdown{display:flex;gap:2em;align-items:center;flex-wrap:wrap}\
+.legend span{display:inline-block;width:.9em;height:.9em;margin-right:.4em;vertical-align:middle}\
+li.error,tr.blocker td{color:#cf222e}li.warning,tr.warning td{color:#9a6700}\
+.muted{color:#656d76}";
+
+const COLORS: [&str; 2] = ["#0969da", "#e34c26"];
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Renders a pie chart of the expected SKU costs as inline SVG.
+fn pie_chart(slices: &[(&str, f64)]) -> String {
+    let total: f64 = slices.iter().map(|slice| slice.1).sum();
+    let mut svg = String::from(
+        "<svg width=\"200\" height=\"200\" viewBox=\"-100 -100 200 200\" role=\"img\">",
+    );
+    if total <= 0f64 {
+        svg.push_str("<circle r=\"90\" fill=\"#d0d7de\"/>");
+    } else {
+        let mut angle = -PI / 2f64;
+        for ((sku, cost), color) in slices.iter().zip(COLORS) {
+            let share = cost / total;
+            if share >= 1f64 {
+                let _ = write!(
+                    svg,
+                    "<circle r=\"90\" fill=\"{}\"><title>{}</title></circle>",
+                    color,
+                    escape(sku)
+                );
+                continue;
+            }
+            if share <= 0f64 {
+                continue;
+            }
+            let end = angle + share * 2f64 * PI;
+            let _ = write!(
+                svg,
+                "<path d=\"M0,0 L{:.3},{:.3} A90,90 0 {} 1 {:.3},{:.3} Z\" fill=\"{}\"><title>{}: {:.1}%</title></path>",
+                90f64 * angle.cos(),
+                90f64 * angle.sin(),
+                if share > 0.5 { 1 } else { 0 },
+                90f64 * end.cos(),
+                90f64 * end.sin(),
+                color,
+                escape(sku),
+                share * 100f64
+            );
+            angle = end;
+        }
+    }
+    svg.push_str("</svg>");
+    svg
+}
+
+fn cluster_section(html: &mut String, report: &ClusterReport) {
+    let total = &report.costs.total;
+    let _ = write!(
+        html,
+        "<section><h2>{}</h2><p>The estimated monthly cost for the workload is <strong>{}</strong>, ranging from {} to {}.</p>",
+        escape(&report.name),
+        OutputFormat::dollars(total.expected),
+        OutputFormat::dollars(total.low),
+        OutputFormat::dollars(total.high)
+    );
+    if let Some(provider) = report.provider {
+        let _ = write!(
+            html,
+            "<p class=\"muted\">The source runs on {}.</p>",
+            provider
+        );
+    }
+
+    html.push_str("<div class=\"breakdown\"><table><tr><th>SKU</th><th>Low</th><th>Expected</th><th>High</th></tr>");
+    for cost in OutputFormat::sku_costs(&report.costs) {
+        let _ = write!(
+            html,
+            "<tr{}><td>{}</td><td class=\"number\">{}</td><td class=\"number\">{}</td><td class=\"number\">{}</td></tr>",
+            if cost.sku == "Total" { " class=\"total\"" } else { "" },
+            escape(cost.sku),
+            OutputFormat::dollars(cost.low),
+            OutputFormat::dollars(cost.expected),
+            OutputFormat::dollars(cost.high)
+        );
+    }
+    html.push_str("</table>");
+    let slices = [
+        ("Request Units", report.costs.request_units.expected),
+        ("Row-based Storage", report.costs.row_based_storage.expected),
+    ];
+    html.push_str(&pie_chart(&slices));
+    html.push_str("<div class=\"legend\">");
+    for ((sku, cost), color) in slices.iter().zip(COLORS) {
+        let _ = write!(
+            html,
+            "<div><span style=\"background:{}\"></span>{} {}</div>",
+            color,
+            escape(sku),
+            OutputFormat::dollars(*cost)
+        );
+    }
+    html.push_str("</div></div>");
+
+    html.push_str("<h3>Workload</h3><table><tr><th>Input</th><th>Value</th></tr>");
+    for (input, value) in OutputFormat::workload_inputs(&report.workload) {
+        let _ = write!(
+            html,
+            "<tr><td>{}</td><td class=\"number\">{}</td></tr>",
+            input, value
+        );
+    }
+    html.push_str("</table>");
+
+    if let Some(issues) = OutputFormat::compatibility_issues(report) {
+        html.push_str("<h3>Compatibility</h3><table><tr><th>Severity</th><th>Code</th><th>Objects</th><th>Message</th></tr>");
+        for issue in issues {
+            let _ = write!(
+                html,
+                "<tr class=\"{}\"><td>{}</td><td><code>{}</code></td><td>{}</td><td>{}</td></tr>",
+                issue.severity.as_str(),
+                issue.severity.as_str(),
+                escape(&issue.code),
+                escape(&issue.objects.join(", ")),
+                escape(&issue.message)
+            );
+        }
+        html.push_str("</table>");
+    }
+
+    if !report.shards.is_empty() {
+        html.push_str("<h3>Shards</h3><table><tr><th>Shard</th><th>RU/h</th><th>RU share</th><th>Storage</th><th>Storage share</th></tr>");
+        for (shard, request_units_share, storage_share) in OutputFormat::shard_shares(report) {
+            let _ = write!(
+                html,
+                "<tr><td>{}</td><td class=\"number\">{}</td><td class=\"number\">{:.1}%</td><td class=\"number\">{}</td><td class=\"number\">{:.1}%</td></tr>",
+                escape(&shard.name),
+                shard.request_units_per_hour,
+                request_units_share,
+                OutputFormat::gibibytes(shard.storage_in_bytes),
+                storage_share
+            );
+        }
+        html.push_str("</table>");
+    }
+
+    if !report.scenarios.is_empty() {
+        html.push_str("<h3>Scenarios</h3><table><tr><th>Scenario</th><th>Request Units</th><th>Row-based Storage</th><th>Total</th><th>Change</th></tr>");
+        for (scenario, _, costs) in OutputFormat::scenarios(report) {
+            let _ = write!(
+                html,
+                "<tr><td>{}</td><td class=\"number\">{}</td><td class=\"number\">{}</td><td class=\"number\">{}</td><td class=\"number\">{}</td></tr>",
+                escape(scenario),
+                OutputFormat::dollars(costs.request_units.expected),
+                OutputFormat::dollars(costs.row_based_storage.expected),
+                OutputFormat::dollars(costs.total.expected),
+                OutputFormat::change(report.costs.total.expected, costs.total.expected)
+            );
+        }
+        html.push_str("</table>");
+    }
+
+    if !report.tables.is_empty() {
+        html.push_str("<h3>Largest tables</h3><table><tr><th>Table</th><th>Rows</th><th>Data</th><th>Indexes</th></tr>");
+        for table in report.tables.iter().take(TOP_TABLES) {
+            let _ = write!(
+                html,
+                "<tr><td><code>{}</code></td><td class=\"number\">{}</td><td class=\"number\">{}</td><td class=\"number\">{}</td></tr>",
+                escape(&table.name),
+                table.rows,
+                OutputFormat::gibibytes(table.data_in_bytes),
+                OutputFormat::gibibytes(table.index_in_bytes)
+            );
+        }
+        html.push_str("</table>");
+    }
+
+    if !report.digests.is_empty() {
+        html.push_str("<h3>Top statements by request units</h3><table><tr><th>Digest</th><th>Statement</th><th>Executions/h</th><th>RU/h</th></tr>");
+        for digest in report.digests.iter().take(TOP_DIGESTS) {
+            let _ = write!(
+                html,
+                "<tr><td><code>{}</code></td><td><code>{}</code></td><td class=\"number\">{}</td><td class=\"number\">{}</td></tr>",
+                escape(&OutputFormat::truncate(&digest.digest, 16)),
+                escape(&digest.sql),
+                digest.executions_per_hour,
+                digest.request_units_per_hour
+            );
+        }
+        html.push_str("</table>");
+    }
+    html.push_str("</section>");
+}
+
+pub(super) fn render(report: &Report, diagnostics: &[Diagnostic]) -> String {
+    let mut html = String::new();
+    let _ = write!(
+        html,
+        "<!DOCTYPE html><html lang=\"en\"><head><meta charset=\"utf-8\"><title>TiDB Serverless Cost Estimation</title><style>{}</style></head><body><h1>TiDB Serverless Cost Estimation</h1><p class=\"muted\">Generated at {}</p>",
+        STYLE,
+        report.generated_at.format("%Y-%m-%d %H:%M:%S UTC")
+    );
+    for cluster in &report.clusters {
+        cluster_section(&mut html, cluster);
+    }
+    if !diagnostics.is_empty() {
+        html.push_str("<h2>Diagnostics</h2><ul>");
+        for diagnostic in diagnostics {
+            let _ = write!(
+                html,
+                "<li class=\"{}\">{}</li>",
+                diagnostic.level.as_str(),
+                escape(&diagnostic.message)
+            );
+        }
+        html.push_str("</ul>");
+    }
+    html.push_str("<h2>Notes</h2><ul>");
+    for note in NOTES {
+        let _ = write!(html, "<li>{}</li>", escape(note));
+    }
+    html.push_str("</ul></body></html>");
+    html
+}
@@ -0,0 +1,130 @@
+use std::fmt::Write;
+
+use super::OutputFormat;
+use crate::compatibility::Severity;
+use crate::report::{Report, UsageReport};
+
+const PREFIX: &str = "tidb_serverless_estimated";
+
+type Usage = fn(&UsageReport) -> u64;
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn family(metrics: &mut String, name: &str, help: &str) {
+    let _ = writeln!(metrics, "# TYPE {}_{} gauge", PREFIX, name);
+    let _ = writeln!(metrics, "# HELP {}_{} {}", PREFIX, name, help);
+}
+
+fn sample(metrics: &mut String, name: &str, labels: &[(&str, &str)], value: f64) {
+    let labels: Vec<String> = labels
+        .iter()
+        .map(|(label, value)| format!("{}=\"{}\"", label, escape(value)))
+        .collect();
+    let _ = writeln!(
+        metrics,
+        "{}_{}{{{}}} {}",
+        PREFIX,
+        name,
+        labels.join(","),
+        value
+    );
+}
+
+/// Renders the estimations in the OpenMetrics text format, one gauge per
+/// cluster labeled with the cluster name and region.
+pub(super) fn render(report: &Report) -> String {
+    let mut metrics = String::new();
+    let region = report.region.as_str();
+    let usages: [(&str, &str, Usage); 3] = [
+        (
+            "request_units_million",
+            "Estimated request units per month in millions.",
+            |usage| usage.request_units_in_million,
+        ),
+        (
+            "row_based_storage_mebibytes",
+            "Estimated row-based storage in MiB.",
+            |usage| usage.row_based_storage_in_mib,
+        ),
+        (
+            "network_egress_mebibytes",
+            "Estimated network egress per month in MiB.",
+            |usage| usage.network_egress_in_mib,
+        ),
+    ];
+    for (name, help, usage) in usages {
+        family(&mut metrics, name, help);
+        for cluster in &report.clusters {
+            sample(
+                &mut metrics,
+                name,
+                &[("cluster", &cluster.name), ("region", region)],
+                usage(&cluster.usage) as f64,
+            );
+        }
+    }
+
+    family(
+        &mut metrics,
+        "monthly_cost_dollars",
+        "Estimated monthly cost in US dollars by SKU and bound.",
+    );
+    for cluster in &report.clusters {
+        for cost in OutputFormat::sku_costs(&cluster.costs) {
+            for (bound, value) in [
+                ("low", cost.low),
+                ("expected", cost.expected),
+                ("high", cost.high),
+            ] {
+                sample(
+                    &mut metrics,
+                    "monthly_cost_dollars",
+                    &[
+                        ("cluster", &cluster.name),
+                        ("region", region),
+                        ("sku", cost.sku),
+                        ("bound", bound),
+                    ],
+                    value,
+                );
+            }
+        }
+    }
+
+    // Only the scanned clusters, so a scan that never ran does not read as
+    // a cluster without blockers.
+    if report
+        .clusters
+        .iter()
+        .any(|cluster| cluster.compatibility.is_some())
+    {
+        family(
+            &mut metrics,
+            "compatibility_issues",
+            "Features of the source schema not supported by TiDB Serverless by severity.",
+        );
+    }
+    for cluster in &report.clusters {
+        for severity in [Severity::Blocker, Severity::Warning] {
+            if let Some(count) = OutputFormat::compatibility_count(cluster, severity) {
+                sample(
+                    &mut metrics,
+                    "compatibility_issues",
+                    &[
+                        ("cluster", &cluster.name),
+                        ("region", region),
+                        ("severity", severity.as_str()),
+                    ],
+                    count as f64,
+                );
+            }
+        }
+    }
+    metrics.push_str("# EOF\n");
+    metrics
+}
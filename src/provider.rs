@@ -0,0 +1,154 @@
+use std::fmt::{Display, Formatter};
+
+use anyhow::Result;
+use regex::Regex;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sqlx::{MySql, Pool};
+
+/// The product or managed service a source database runs on.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize, JsonSchema)]
+pub enum Provider {
+    #[serde(rename = "mysql")]
+    MySQL,
+    #[serde(rename = "mariadb")]
+    MariaDB,
+    #[serde(rename = "tidb")]
+    TiDB,
+    #[serde(rename = "tidb_serverless")]
+    TiDBServerless,
+    #[serde(rename = "aurora_mysql")]
+    AuroraMySQL,
+    #[serde(rename = "rds_mysql")]
+    RDSMySQL,
+    #[serde(rename = "cloud_sql")]
+    CloudSQL,
+    #[serde(rename = "azure_mysql")]
+    AzureMySQL,
+    #[serde(rename = "polardb")]
+    PolarDB,
+}
+
+impl Display for Provider {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Provider::MySQL => "MySQL",
+            Provider::MariaDB => "MariaDB",
+            Provider::TiDB => "TiDB",
+            Provider::TiDBServerless => "TiDB Serverless",
+            Provider::AuroraMySQL => "Amazon Aurora MySQL",
+            Provider::RDSMySQL => "Amazon RDS for MySQL",
+            Provider::CloudSQL => "Google Cloud SQL for MySQL",
+            Provider::AzureMySQL => "Azure Database for MySQL",
+            Provider::PolarDB => "Alibaba Cloud PolarDB for MySQL",
+        })
+    }
+}
+
+impl Provider {
+    pub fn is_tidb(&self) -> bool {
+        matches!(self, Provider::TiDB | Provider::TiDBServerless)
+    }
+
+    /// Whether the InnoDB tablespace file sizes reflect the storage. Aurora
+    /// and PolarDB keep the data on a shared distributed volume instead.
+    pub fn has_tablespace_files(&self) -> bool {
+        !matches!(self, Provider::AuroraMySQL | Provider::PolarDB)
+    }
+
+    /// How to enable the Performance Schema, which managed services only
+    /// allow through their parameters.
+    fn performance_schema_instructions(&self) -> &'static str {
+        match self {
+            Provider::MariaDB => "For instructions, see this guide: https://mariadb.com/kb/en/performance-schema-overview/#activating-the-performance-schema",
+            Provider::AuroraMySQL => "Set 'performance_schema' to 1 in the DB cluster parameter group, or turn on Performance Insights, then reboot the instances. For instructions, see this guide: https://docs.aws.amazon.com/AmazonRDS/latest/AuroraUserGuide/USER_PerfInsights.EnableMySQL.html",
+            Provider::RDSMySQL => "Set 'performance_schema' to 1 in the DB parameter group, or turn on Performance Insights, then reboot the instance. For instructions, see this guide: https://docs.aws.amazon.com/AmazonRDS/latest/UserGuide/USER_PerfInsights.EnableMySQL.html",
+            Provider::CloudSQL => "Set the 'performance_schema' database flag to on, which restarts the instance. For instructions, see this guide: https://cloud.google.com/sql/docs/mysql/flags",
+            Provider::AzureMySQL => "Set the 'performance_schema' server parameter to ON, then restart the server. For instructions, see this guide: https://learn.microsoft.com/azure/mysql/flexible-server/concepts-server-parameters",
+            Provider::PolarDB => "Set 'loose_performance_schema' to ON in the cluster parameters, then restart the cluster. For instructions, see this guide: https://www.alibabacloud.com/help/en/polardb/polardb-for-mysql/user-guide/performance-schema",
+            _ => "For instructions, see this guide: https://dev.mysql.com/doc/refman/5.7/en/performance-schema-startup-configuration.html",
+        }
+    }
+
+    /// The message explaining how to make the workload of a source without
+    /// the Performance Schema collectable.
+    pub fn performance_schema_disabled(&self) -> String {
+        format!("Please enable the 'Performance Schema' on your {} server and keep it active for at least a full business day to ensure comprehensive workload coverage. {}", self, self.performance_schema_instructions())
+    }
+}
+
+async fn check_version_signature(pool: &Pool<MySql>, pattern: &str) -> Result<bool> {
+    let version: (String,) = sqlx::query_as("SELECT version()").fetch_one(pool).await?;
+    Ok(Regex::new(pattern)?.find(&version.0).is_some())
+}
+
+async fn has_variable(pool: &Pool<MySql>, pattern: &str) -> Result<bool> {
+    let variables: Vec<(String, String)> =
+        sqlx::query_as(&format!("SHOW VARIABLES LIKE '{}'", pattern))
+            .fetch_all(pool)
+            .await?;
+    Ok(!variables.is_empty())
+}
+
+async fn check_variable_prefix(pool: &Pool<MySql>, variable: &str, prefix: &str) -> Result<bool> {
+    let value: Option<(String, String)> =
+        sqlx::query_as(&format!("SHOW VARIABLES LIKE '{}'", variable))
+            .fetch_optional(pool)
+            .await?;
+    Ok(value.is_some_and(|(_, value)| value.starts_with(prefix)))
+}
+
+/// Detects the product from the version signature, the functions and the
+/// variables specific to each managed service.
+pub async fn detect(pool: &Pool<MySql>) -> Result<Provider> {
+    if check_version_signature(pool, "^\\d+\\.\\d+\\.\\d+-(?i)TiDB(?-i)-.*").await? {
+        return Ok(
+            if check_version_signature(
+                pool,
+                "^\\d+\\.\\d+\\.\\d+-(?i)TiDB(?-i)-v\\d+\\.\\d+\\.\\d+-(?i)serverless(?-i).*",
+            )
+            .await?
+            {
+                Provider::TiDBServerless
+            } else {
+                Provider::TiDB
+            },
+        );
+    }
+    if check_version_signature(pool, "^\\d+\\.\\d+\\.\\d+-(?i)MariaDB(?-i)-.*").await? {
+        return Ok(Provider::MariaDB);
+    }
+    // The function only exists on Aurora, anywhere else the query fails.
+    let aurora: Result<(String,), _> = sqlx::query_as("SELECT aurora_version()")
+        .fetch_one(pool)
+        .await;
+    if aurora.is_ok() {
+        return Ok(Provider::AuroraMySQL);
+    }
+    if has_variable(pool, "polar\\_%").await? {
+        return Ok(Provider::PolarDB);
+    }
+    if check_version_signature(pool, "^\\d+\\.\\d+\\.\\d+-google").await? {
+        return Ok(Provider::CloudSQL);
+    }
+    if has_variable(pool, "aad\\_auth%").await? || has_variable(pool, "azure\\_%").await? {
+        return Ok(Provider::AzureMySQL);
+    }
+    if check_variable_prefix(pool, "basedir", "/rdsdbbin/").await? {
+        return Ok(Provider::RDSMySQL);
+    }
+    Ok(Provider::MySQL)
+}
+
+/// Whether the source is a read-only replica. Aurora readers are flagged by
+/// `innodb_read_only`, the replicas of the other products by `read_only`.
+pub async fn is_replica(pool: &Pool<MySql>, provider: Provider) -> Result<bool> {
+    let variable = match provider {
+        Provider::AuroraMySQL => "@@innodb_read_only",
+        _ => "@@read_only",
+    };
+    let (read_only,): (i64,) = sqlx::query_as(&format!("SELECT CAST({} AS SIGNED)", variable))
+        .fetch_one(pool)
+        .await?;
+    Ok(read_only != 0)
+}
@@ -0,0 +1,323 @@
+use chrono::{DateTime, Utc};
+use schemars::{schema_for, JsonSchema};
+use serde::{Deserialize, Serialize};
+
+use crate::calculator::{WorkloadEstimation, WorkloadUsage, PRICING_VERSION};
+use crate::compatibility::CompatibilityIssue;
+use crate::explain::Trace;
+use crate::model::RequestUnitModel;
+use crate::provider::Provider;
+use crate::source::{
+    RequestDescription, ShardContribution, StorageDescription, StorageMeasurement,
+    TableDescription, WorkloadDescription,
+};
+
+/// Version of the machine-readable report schema. It is bumped whenever a field
+/// is removed, renamed or changes its meaning; new optional fields keep it.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// The machine-readable report of an estimation run, decoupled from the
+/// internal workload and estimation types.
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct Report {
+    pub schema_version: u32,
+    pub tool_version: String,
+    pub generated_at: DateTime<Utc>,
+    pub region: String,
+    pub pricing_version: String,
+    pub model: ModelReport,
+    pub clusters: Vec<ClusterReport>,
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct ModelReport {
+    pub name: String,
+    pub version: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct ClusterReport {
+    pub name: String,
+    pub collected_at: DateTime<Utc>,
+    pub workload: WorkloadInput,
+    pub usage: UsageReport,
+    pub costs: CostReport,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub digests: Vec<DigestReport>,
+    /// The statistics of every table, largest first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tables: Vec<TableReport>,
+    /// The what-if scenarios estimated on top of the collected workload.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub scenarios: Vec<ScenarioReport>,
+    /// The product or managed service the source runs on.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<Provider>,
+    /// Features of the source schema TiDB Serverless does not support, none
+    /// when the schema was not scanned.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compatibility: Option<Vec<CompatibilityIssue>>,
+    /// The contribution of every shard when the cluster consolidates a shard group.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub shards: Vec<ShardContribution>,
+    #[serde(default, skip_deserializing, skip_serializing_if = "Option::is_none")]
+    #[schemars(with = "Option<serde_json::Value>")]
+    pub explain: Option<Trace>,
+}
+
+/// The workload collected from the source, per hour unless stated otherwise.
+#[derive(Debug, Default, Clone, Deserialize, Serialize, JsonSchema)]
+pub struct WorkloadInput {
+    pub read_requests_per_hour: u64,
+    pub read_bytes_per_hour: u64,
+    pub write_requests_per_hour: u64,
+    pub write_bytes_per_hour: u64,
+    pub egress_bytes_per_hour: u64,
+    pub storage_data_in_bytes: u64,
+    pub storage_index_in_bytes: u64,
+    /// The queries sending the read and write requests, when known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub read_queries_per_hour: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub write_queries_per_hour: Option<u64>,
+    /// Request units measured by the source, only available on TiDB Serverless.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_units_per_hour: Option<u64>,
+    /// How the storage was measured.
+    #[serde(default)]
+    pub storage_measurement: StorageMeasurement,
+    /// The storage on the source database, when the storage above is
+    /// converted to the size estimated for the TiDB encoding.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_storage_in_bytes: Option<u64>,
+}
+
+/// The monthly usage billed by TiDB Serverless.
+#[derive(Debug, Default, Clone, Deserialize, Serialize, JsonSchema)]
+pub struct UsageReport {
+    pub request_units_in_million: u64,
+    pub row_based_storage_in_mib: u64,
+    pub network_egress_in_mib: u64,
+}
+
+#[derive(Debug, Default, Clone, Copy, Deserialize, Serialize, JsonSchema)]
+pub struct CostBounds {
+    pub low: f64,
+    pub expected: f64,
+    pub high: f64,
+}
+
+/// The monthly cost in US dollars, the total is after the free credit.
+#[derive(Debug, Default, Clone, Deserialize, Serialize, JsonSchema)]
+pub struct CostReport {
+    pub request_units: CostBounds,
+    pub row_based_storage: CostBounds,
+    pub free_credit: f64,
+    pub total: CostBounds,
+}
+
+#[derive(Debug, Default, Clone, Deserialize, Serialize, JsonSchema)]
+pub struct ScenarioReport {
+    pub name: String,
+    pub workload: WorkloadInput,
+    pub usage: UsageReport,
+    pub costs: CostReport,
+}
+
+#[derive(Debug, Default, Clone, Deserialize, Serialize, JsonSchema)]
+pub struct DigestReport {
+    pub digest: String,
+    pub statement_type: String,
+    pub sql: String,
+    pub executions_per_hour: u64,
+    pub request_units_per_hour: u64,
+}
+
+#[derive(Debug, Default, Clone, Deserialize, Serialize, JsonSchema)]
+pub struct TableReport {
+    pub name: String,
+    pub rows: u64,
+    pub data_in_bytes: u64,
+    pub index_in_bytes: u64,
+}
+
+impl From<&WorkloadDescription> for WorkloadInput {
+    fn from(workload: &WorkloadDescription) -> Self {
+        Self {
+            read_requests_per_hour: workload.read.requests_per_hour.unwrap_or(0),
+            read_bytes_per_hour: workload.read.bytes_per_hour,
+            write_requests_per_hour: workload.write.requests_per_hour.unwrap_or(0),
+            write_bytes_per_hour: workload.write.bytes_per_hour,
+            egress_bytes_per_hour: workload.egress.bytes_per_hour,
+            storage_data_in_bytes: workload.storage.data_in_bytes,
+            storage_index_in_bytes: workload.storage.index_in_bytes,
+            read_queries_per_hour: workload.read.queries_per_hour,
+            write_queries_per_hour: workload.write.queries_per_hour,
+            request_units_per_hour: workload.request_units_per_hour,
+            storage_measurement: workload.storage.measurement,
+            source_storage_in_bytes: workload.storage.source_in_bytes,
+        }
+    }
+}
+
+impl From<&WorkloadUsage> for UsageReport {
+    fn from(usage: &WorkloadUsage) -> Self {
+        Self {
+            request_units_in_million: usage.request_units_in_million,
+            row_based_storage_in_mib: usage.row_based_storage_in_mib,
+            network_egress_in_mib: usage.network_egress_in_mib,
+        }
+    }
+}
+
+impl From<&WorkloadEstimation> for CostReport {
+    fn from(estimation: &WorkloadEstimation) -> Self {
+        let total = estimation.total_cost_range();
+        Self {
+            request_units: CostBounds {
+                low: estimation.request_units_cost_range.low,
+                expected: estimation.request_units_cost,
+                high: estimation.request_units_cost_range.high,
+            },
+            row_based_storage: CostBounds {
+                low: estimation.storage_cost_range.low,
+                expected: estimation.storage_cost,
+                high: estimation.storage_cost_range.high,
+            },
+            free_credit: estimation.free_credit,
+            total: CostBounds {
+                low: total.low,
+                expected: estimation.total_cost(),
+                high: total.high,
+            },
+        }
+    }
+}
+
+/// The workload recorded in a report, re-estimated without the source
+/// database. The low and high bounds are not recorded, so they are lost.
+impl From<&ClusterReport> for WorkloadDescription {
+    fn from(cluster: &ClusterReport) -> Self {
+        let workload = &cluster.workload;
+        Self {
+            name: cluster.name.clone(),
+            collected_at: cluster.collected_at,
+            read: RequestDescription {
+                requests_per_hour: Some(workload.read_requests_per_hour),
+                bytes_per_hour: workload.read_bytes_per_hour,
+                queries_per_hour: workload.read_queries_per_hour,
+            },
+            write: RequestDescription {
+                requests_per_hour: Some(workload.write_requests_per_hour),
+                bytes_per_hour: workload.write_bytes_per_hour,
+                queries_per_hour: workload.write_queries_per_hour,
+            },
+            egress: RequestDescription {
+                requests_per_hour: None,
+                bytes_per_hour: workload.egress_bytes_per_hour,
+                queries_per_hour: None,
+            },
+            storage: StorageDescription {
+                data_in_bytes: workload.storage_data_in_bytes,
+                index_in_bytes: workload.storage_index_in_bytes,
+                measurement: workload.storage_measurement,
+                source_in_bytes: workload.source_storage_in_bytes,
+            },
+            request_units_per_hour: workload.request_units_per_hour,
+            provider: cluster.provider,
+            tables: cluster
+                .tables
+                .iter()
+                .map(|table| TableDescription {
+                    name: table.name.clone(),
+                    rows: table.rows,
+                    data_in_bytes: table.data_in_bytes,
+                    index_in_bytes: table.index_in_bytes,
+                })
+                .collect(),
+            compatibility: cluster.compatibility.clone(),
+            shards: cluster.shards.clone(),
+            ..Default::default()
+        }
+    }
+}
+
+impl ClusterReport {
+    fn new(
+        mut workload: WorkloadDescription,
+        mut estimation: WorkloadEstimation,
+        explain: bool,
+    ) -> Self {
+        let explain = explain.then(|| {
+            let mut trace = Trace::new("explain");
+            trace.push(std::mem::take(&mut workload.trace));
+            trace.push(std::mem::take(&mut estimation.trace));
+            trace
+        });
+        Self {
+            workload: WorkloadInput::from(&workload),
+            usage: UsageReport::from(&estimation.usage),
+            costs: CostReport::from(&estimation),
+            digests: workload
+                .digests
+                .into_iter()
+                .map(|digest| DigestReport {
+                    digest: digest.digest,
+                    statement_type: digest.statement_type,
+                    sql: digest.sql,
+                    executions_per_hour: digest.executions_per_hour,
+                    request_units_per_hour: digest.request_units_per_hour,
+                })
+                .collect(),
+            tables: workload
+                .tables
+                .into_iter()
+                .map(|table| TableReport {
+                    name: table.name,
+                    rows: table.rows,
+                    data_in_bytes: table.data_in_bytes,
+                    index_in_bytes: table.index_in_bytes,
+                })
+                .collect(),
+            name: workload.name,
+            collected_at: workload.collected_at,
+            scenarios: Vec::new(),
+            provider: workload.provider,
+            compatibility: workload.compatibility,
+            shards: workload.shards,
+            explain,
+        }
+    }
+}
+
+impl Report {
+    pub fn new(
+        region: &str,
+        model: &RequestUnitModel,
+        workloads: Vec<WorkloadDescription>,
+        estimations: Vec<WorkloadEstimation>,
+        explain: bool,
+    ) -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            tool_version: env!("CARGO_PKG_VERSION").into(),
+            generated_at: Utc::now(),
+            region: region.into(),
+            pricing_version: PRICING_VERSION.into(),
+            model: ModelReport {
+                name: model.name.clone(),
+                version: model.version.clone(),
+            },
+            clusters: workloads
+                .into_iter()
+                .zip(estimations)
+                .map(|(workload, estimation)| ClusterReport::new(workload, estimation, explain))
+                .collect(),
+        }
+    }
+
+    /// The JSON Schema of the report generated from the Rust types.
+    pub fn schema() -> String {
+        serde_json::to_string_pretty(&schema_for!(Report)).unwrap()
+    }
+}
@@ -0,0 +1,323 @@
+use anyhow::Result;
+use serde::Deserialize;
+
+use crate::calculator;
+use crate::model::RequestUnitModel;
+use crate::report::{CostReport, ScenarioReport, UsageReport, WorkloadInput};
+use crate::source::{
+    RequestDescription, StorageDescription, WorkloadBounds, WorkloadDescription, SECONDS_PER_HOUR,
+};
+
+/// Changes a value of the workload, either scaling it by a multiplier or
+/// replacing it with an absolute value, e.g. `2.0` or `{ value: 1000 }`.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(untagged)]
+pub enum Adjustment {
+    Multiplier(f64),
+    Override { value: f64 },
+}
+
+impl Adjustment {
+    fn apply(&self, value: u64) -> u64 {
+        match *self {
+            Adjustment::Multiplier(multiplier) => (value as f64 * multiplier) as u64,
+            Adjustment::Override { value } => value as u64,
+        }
+    }
+}
+
+fn adjust(adjustment: Option<Adjustment>, value: u64) -> u64 {
+    adjustment.map_or(value, |adjustment| adjustment.apply(value))
+}
+
+fn scale(value: u64, ratio: f64) -> u64 {
+    if ratio == 1f64 {
+        value
+    } else {
+        (value as f64 * ratio) as u64
+    }
+}
+
+/// Changes the queries per second, keeping the requests and bytes of a query
+/// as collected, and returns the ratio of the queries to scale the egress
+/// with. Without the collected queries, a query is one request.
+fn adjust_queries(
+    adjustment: Option<Adjustment>,
+    request: &RequestDescription,
+) -> (RequestDescription, f64) {
+    let queries_per_hour = request
+        .queries_per_hour
+        .or(request.requests_per_hour)
+        .unwrap_or(0);
+    let adjusted = match adjustment {
+        None => queries_per_hour,
+        Some(Adjustment::Multiplier(multiplier)) => (queries_per_hour as f64 * multiplier) as u64,
+        Some(Adjustment::Override { value }) => (value * SECONDS_PER_HOUR) as u64,
+    };
+    // Without queries to divide by, the values are kept as collected.
+    let ratio = if adjusted == queries_per_hour || queries_per_hour == 0 {
+        1f64
+    } else {
+        adjusted as f64 / queries_per_hour as f64
+    };
+    let adjusted_request = RequestDescription {
+        requests_per_hour: request
+            .requests_per_hour
+            .map(|requests| match queries_per_hour {
+                0 => adjusted,
+                _ => scale(requests, ratio),
+            }),
+        bytes_per_hour: scale(request.bytes_per_hour, ratio),
+        queries_per_hour: request.queries_per_hour.map(|_| adjusted),
+    };
+    (adjusted_request, ratio)
+}
+
+/// A named what-if change applied to every collected workload before the
+/// estimation. Values not mentioned are kept as collected.
+#[derive(Debug, Default, Deserialize)]
+pub struct Scenario {
+    pub name: String,
+    /// Converted to requests and bytes with the regions and bytes of a
+    /// collected query, the requests and bytes below apply on top.
+    #[serde(default)]
+    pub read_queries_per_second: Option<Adjustment>,
+    #[serde(default)]
+    pub write_queries_per_second: Option<Adjustment>,
+    #[serde(default)]
+    pub read_requests_per_hour: Option<Adjustment>,
+    #[serde(default)]
+    pub read_bytes_per_hour: Option<Adjustment>,
+    #[serde(default)]
+    pub write_requests_per_hour: Option<Adjustment>,
+    #[serde(default)]
+    pub write_bytes_per_hour: Option<Adjustment>,
+    #[serde(default)]
+    pub egress_bytes_per_hour: Option<Adjustment>,
+    /// Applies to data and indexes, an override is split between them as collected.
+    #[serde(default)]
+    pub storage_in_bytes: Option<Adjustment>,
+}
+
+impl Scenario {
+    pub fn load(file: &str) -> Result<Vec<Self>> {
+        crate::config::load(file, "scenario file")
+    }
+
+    fn adjust_storage(&self, storage: &StorageDescription) -> StorageDescription {
+        let total = storage.data_in_bytes + storage.index_in_bytes;
+        let adjusted = adjust(self.storage_in_bytes, total);
+        if total == 0 {
+            return StorageDescription {
+                data_in_bytes: adjusted,
+                index_in_bytes: 0,
+                measurement: storage.measurement,
+                source_in_bytes: storage.source_in_bytes,
+            };
+        }
+        let data_in_bytes = (adjusted as f64 * storage.data_in_bytes as f64 / total as f64) as u64;
+        StorageDescription {
+            data_in_bytes,
+            index_in_bytes: adjusted - data_in_bytes,
+            measurement: storage.measurement,
+            source_in_bytes: storage
+                .source_in_bytes
+                .map(|source| (source as f64 * adjusted as f64 / total as f64) as u64),
+        }
+    }
+
+    /// Request units of the workload according to the model, used to scale
+    /// request units measured by the source along with the adjusted requests.
+    fn modeled_request_units(model: &RequestUnitModel, workload: &WorkloadDescription) -> f64 {
+        model.read_request_units(
+            workload.read.requests_per_hour.unwrap_or(0) as f64,
+            workload.read.bytes_per_hour as f64,
+        ) + model.write_request_units(
+            workload.write.requests_per_hour.unwrap_or(0) as f64,
+            workload.write.bytes_per_hour as f64,
+        )
+    }
+
+    pub fn apply(
+        &self,
+        model: &RequestUnitModel,
+        workload: &WorkloadDescription,
+    ) -> WorkloadDescription {
+        let (read, read_ratio) = adjust_queries(self.read_queries_per_second, &workload.read);
+        let (write, _) = adjust_queries(self.write_queries_per_second, &workload.write);
+        let mut adjusted = WorkloadDescription {
+            name: workload.name.clone(),
+            collected_at: workload.collected_at,
+            read: RequestDescription {
+                requests_per_hour: read
+                    .requests_per_hour
+                    .map(|requests| adjust(self.read_requests_per_hour, requests)),
+                bytes_per_hour: adjust(self.read_bytes_per_hour, read.bytes_per_hour),
+                queries_per_hour: read.queries_per_hour,
+            },
+            write: RequestDescription {
+                requests_per_hour: write
+                    .requests_per_hour
+                    .map(|requests| adjust(self.write_requests_per_hour, requests)),
+                bytes_per_hour: adjust(self.write_bytes_per_hour, write.bytes_per_hour),
+                queries_per_hour: write.queries_per_hour,
+            },
+            egress: RequestDescription {
+                requests_per_hour: workload.egress.requests_per_hour,
+                bytes_per_hour: adjust(
+                    self.egress_bytes_per_hour,
+                    scale(workload.egress.bytes_per_hour, read_ratio),
+                ),
+                queries_per_hour: None,
+            },
+            storage: self.adjust_storage(&workload.storage),
+            bounds: workload.bounds.as_ref().map(|bounds| {
+                Box::new(WorkloadBounds {
+                    low: self.apply(model, &bounds.low),
+                    high: self.apply(model, &bounds.high),
+                })
+            }),
+            ..Default::default()
+        };
+        adjusted.request_units_per_hour = workload.request_units_per_hour.map(|request_units| {
+            let before = Self::modeled_request_units(model, workload);
+            let after = Self::modeled_request_units(model, &adjusted);
+            if before > 0f64 {
+                (request_units as f64 * after / before) as u64
+            } else {
+                request_units
+            }
+        });
+        adjusted
+    }
+}
+
+/// Estimates every scenario for every workload, in the order of the workloads.
+pub fn evaluate(
+    region: &str,
+    model: &RequestUnitModel,
+    scenarios: &[Scenario],
+    workloads: &[WorkloadDescription],
+) -> Result<Vec<Vec<ScenarioReport>>> {
+    workloads
+        .iter()
+        .map(|workload| {
+            let adjusted: Vec<WorkloadDescription> = scenarios
+                .iter()
+                .map(|scenario| scenario.apply(model, workload))
+                .collect();
+            let estimations = calculator::estimate(region, model, &adjusted)?;
+            Ok(scenarios
+                .iter()
+                .zip(adjusted.iter().zip(estimations))
+                .map(|(scenario, (workload, estimation))| ScenarioReport {
+                    name: scenario.name.clone(),
+                    workload: WorkloadInput::from(workload),
+                    usage: UsageReport::from(&estimation.usage),
+                    costs: CostReport::from(&estimation),
+                })
+                .collect())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `scale` times an hour of 3600 reads of three regions, sending 512
+    /// bytes each, and 1800 writes of two regions.
+    fn workload(scale: u64) -> WorkloadDescription {
+        WorkloadDescription {
+            read: RequestDescription {
+                requests_per_hour: Some(10800 * scale),
+                bytes_per_hour: 3600 * 4096 * scale,
+                queries_per_hour: Some(3600 * scale),
+            },
+            write: RequestDescription {
+                requests_per_hour: Some(3600 * scale),
+                bytes_per_hour: 1800 * 1024 * scale,
+                queries_per_hour: Some(1800 * scale),
+            },
+            egress: RequestDescription {
+                bytes_per_hour: 3600 * 512 * scale,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    /// The monthly cost of the request units, egress included.
+    fn request_units_cost(model: &RequestUnitModel, workload: WorkloadDescription) -> f64 {
+        calculator::estimate("us-east-1", model, &[workload]).unwrap()[0].request_units_cost
+    }
+
+    #[test]
+    fn double_traffic_doubles_request_units() {
+        let model = RequestUnitModel::default();
+        // Large enough for the rounding to whole millions of request units to vanish.
+        let before = request_units_cost(&model, workload(10000));
+        let workload = workload(10000);
+        for scenario in [
+            Scenario {
+                read_queries_per_second: Some(Adjustment::Multiplier(2f64)),
+                write_queries_per_second: Some(Adjustment::Multiplier(2f64)),
+                ..Default::default()
+            },
+            Scenario {
+                read_requests_per_hour: Some(Adjustment::Multiplier(2f64)),
+                read_bytes_per_hour: Some(Adjustment::Multiplier(2f64)),
+                write_requests_per_hour: Some(Adjustment::Multiplier(2f64)),
+                write_bytes_per_hour: Some(Adjustment::Multiplier(2f64)),
+                egress_bytes_per_hour: Some(Adjustment::Multiplier(2f64)),
+                ..Default::default()
+            },
+        ] {
+            let after = request_units_cost(&model, scenario.apply(&model, &workload));
+            assert!(
+                (after / before - 2f64).abs() < 1e-4,
+                "{} is not twice {}",
+                after,
+                before
+            );
+        }
+    }
+
+    #[test]
+    fn queries_per_second_use_regions_per_query() {
+        let scenario = Scenario {
+            read_queries_per_second: Some(Adjustment::Override { value: 10f64 }),
+            ..Default::default()
+        };
+        let adjusted = scenario.apply(&RequestUnitModel::default(), &workload(1));
+        assert_eq!(adjusted.read.queries_per_hour, Some(36000));
+        assert_eq!(adjusted.read.requests_per_hour, Some(108000));
+        assert_eq!(adjusted.read.bytes_per_hour, 36000 * 4096);
+        assert_eq!(adjusted.egress.bytes_per_hour, 36000 * 512);
+        assert_eq!(adjusted.write.requests_per_hour, Some(3600));
+    }
+
+    #[test]
+    fn queries_per_second_may_be_fractional() {
+        let scenario: Vec<Scenario> =
+            serde_yaml::from_str("- name: quiet\n  read_queries_per_second: { value: 0.5 }\n")
+                .unwrap();
+        let adjusted = scenario[0].apply(&RequestUnitModel::default(), &workload(1));
+        assert_eq!(adjusted.read.queries_per_hour, Some(1800));
+        assert_eq!(adjusted.read.requests_per_hour, Some(5400));
+        assert_eq!(adjusted.egress.bytes_per_hour, 1800 * 512);
+    }
+
+    #[test]
+    fn queries_per_second_without_collected_queries() {
+        let mut workload = workload(1);
+        workload.read.queries_per_hour = None;
+        let scenario = Scenario {
+            read_queries_per_second: Some(Adjustment::Override { value: 1f64 }),
+            ..Default::default()
+        };
+        let adjusted = scenario.apply(&RequestUnitModel::default(), &workload);
+        assert_eq!(adjusted.read.requests_per_hour, Some(3600));
+        assert_eq!(adjusted.read.bytes_per_hour, 3600 * 4096 / 3);
+    }
+}
@@ -0,0 +1,397 @@
+use std::collections::HashMap;
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Result};
+use axum::extract::rejection::JsonRejection;
+use axum::extract::{Path, Request, State};
+use axum::http::header::AUTHORIZATION;
+use axum::http::StatusCode;
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Semaphore;
+use uuid::Uuid;
+
+use crate::calculator;
+use crate::model::RequestUnitModel;
+use crate::output::ExitCode;
+use crate::report::Report;
+use crate::source::{
+    self, Diagnostics, LoadOptions, WorkloadDescription, WorkloadSourceConfiguration,
+};
+
+/// Finished jobs are kept this long for their report to be fetched.
+const RETENTION: Duration = Duration::from_secs(3600);
+
+/// An estimation submitted to the service, either the databases to collect
+/// the workload from or a json report saved earlier to re-estimate.
+#[derive(Deserialize)]
+struct EstimationRequest {
+    region: String,
+    #[serde(default)]
+    sources: Vec<WorkloadSourceConfiguration>,
+    #[serde(default)]
+    snapshot: Option<Report>,
+    #[serde(default)]
+    serverless: bool,
+    #[serde(default)]
+    compatibility: bool,
+}
+
+#[derive(Clone, Copy, Default, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Status {
+    #[default]
+    Pending,
+    Running,
+    Succeeded,
+    Failed,
+}
+
+#[derive(Serialize)]
+struct JobDiagnostic {
+    level: &'static str,
+    source: String,
+    code: String,
+    message: String,
+}
+
+#[derive(Default)]
+struct Job {
+    status: Status,
+    diagnostics: Vec<JobDiagnostic>,
+    report: Option<Report>,
+    finished_at: Option<Instant>,
+}
+
+/// The state of a job returned to the client. The source configurations are
+/// never part of it, so credentials are not echoed back.
+#[derive(Serialize)]
+struct JobView<'a> {
+    id: &'a str,
+    status: Status,
+    #[serde(skip_serializing_if = "<[JobDiagnostic]>::is_empty")]
+    diagnostics: &'a [JobDiagnostic],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    report: Option<&'a Report>,
+}
+
+impl Job {
+    fn view<'a>(&'a self, id: &'a str) -> JobView<'a> {
+        JobView {
+            id,
+            status: self.status,
+            diagnostics: &self.diagnostics,
+            report: self.report.as_ref(),
+        }
+    }
+}
+
+/// Collects the diagnostics of a job, attributed to the source being loaded.
+#[derive(Default)]
+struct Collector {
+    source: Mutex<String>,
+    diagnostics: Mutex<Vec<JobDiagnostic>>,
+}
+
+impl Collector {
+    fn set_source(&self, source: String) {
+        *self.source.lock().unwrap_or_else(PoisonError::into_inner) = source;
+    }
+
+    fn push(&self, level: &'static str, code: &str, message: &str) {
+        let source = self
+            .source
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone();
+        self.diagnostics
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(JobDiagnostic {
+                level,
+                source,
+                code: code.into(),
+                message: message.into(),
+            });
+    }
+
+    fn into_diagnostics(self) -> Vec<JobDiagnostic> {
+        self.diagnostics
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Diagnostics for Collector {
+    fn error(&self, code: &str, message: &str) {
+        self.push("error", code, message);
+    }
+
+    fn warn(&self, code: &str, message: &str) {
+        self.push("warning", code, message);
+    }
+}
+
+/// The hosts the sources may connect to, as patterns where `*` matches any
+/// characters. Without patterns, only snapshots are accepted.
+struct AllowedHosts(Vec<Regex>);
+
+impl AllowedHosts {
+    fn new(patterns: &[String]) -> Result<Self> {
+        let mut hosts = Vec::with_capacity(patterns.len());
+        for pattern in patterns {
+            hosts.push(Regex::new(&format!(
+                "(?i)^{}$",
+                regex::escape(pattern).replace("\\*", ".*")
+            ))?);
+        }
+        Ok(Self(hosts))
+    }
+
+    fn allows(&self, host: &str) -> bool {
+        self.0.iter().any(|pattern| pattern.is_match(host))
+    }
+}
+
+struct Service {
+    model: RequestUnitModel,
+    jobs: Mutex<HashMap<String, Job>>,
+    permits: Semaphore,
+    max_jobs: usize,
+    token: String,
+    allowed_hosts: AllowedHosts,
+}
+
+impl Service {
+    fn jobs(&self) -> MutexGuard<'_, HashMap<String, Job>> {
+        self.jobs.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn update(&self, id: &str, update: impl FnOnce(&mut Job)) {
+        if let Some(job) = self.jobs().get_mut(id) {
+            update(job);
+        }
+    }
+}
+
+fn error(status: StatusCode, code: &str, message: &str) -> Response {
+    (
+        status,
+        Json(serde_json::json!({ "code": code, "message": message })),
+    )
+        .into_response()
+}
+
+/// Compares in constant time, so the response time does not leak the token.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+/// Rejects the requests without the bearer token the service is started with.
+async fn authorize(State(service): State<Arc<Service>>, request: Request, next: Next) -> Response {
+    let authorized = request
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|token| constant_time_eq(token.as_bytes(), service.token.as_bytes()));
+    if !authorized {
+        return error(
+            StatusCode::UNAUTHORIZED,
+            "unauthorized",
+            "The request lacks the bearer token of the service.",
+        );
+    }
+    next.run(request).await
+}
+
+async fn submit(
+    State(service): State<Arc<Service>>,
+    request: Result<Json<EstimationRequest>, JsonRejection>,
+) -> Response {
+    // The rejection may quote the submitted values, including passwords.
+    let Ok(Json(request)) = request else {
+        return error(
+            StatusCode::BAD_REQUEST,
+            "invalid_request",
+            "The request body is not a valid estimation request.",
+        );
+    };
+    if let Err(e) = calculator::check_region(&request.region) {
+        return error(
+            StatusCode::BAD_REQUEST,
+            ExitCode::InvalidRegion.code(),
+            &e.to_string(),
+        );
+    }
+    if request.sources.is_empty() == request.snapshot.is_none() {
+        return error(
+            StatusCode::BAD_REQUEST,
+            "invalid_request",
+            "Either the sources or a snapshot must be given.",
+        );
+    }
+    // Checked before connecting, so the service cannot probe arbitrary hosts.
+    for configuration in &request.sources {
+        let hosts = match configuration.hosts() {
+            Ok(hosts) => hosts,
+            Err(e) => return error(StatusCode::BAD_REQUEST, "invalid_request", &e.to_string()),
+        };
+        if let Some(host) = hosts
+            .iter()
+            .find(|host| !service.allowed_hosts.allows(host))
+        {
+            return error(
+                StatusCode::FORBIDDEN,
+                "host_not_allowed",
+                &format!("The host '{}' is not among the allowed hosts.", host),
+            );
+        }
+    }
+
+    let id = Uuid::new_v4().to_string();
+    {
+        let mut jobs = service.jobs();
+        jobs.retain(|_, job| {
+            job.finished_at
+                .is_none_or(|finished_at| finished_at.elapsed() < RETENTION)
+        });
+        let unfinished = jobs
+            .values()
+            .filter(|job| job.finished_at.is_none())
+            .count();
+        if unfinished >= service.max_jobs {
+            return error(
+                StatusCode::TOO_MANY_REQUESTS,
+                "too_many_jobs",
+                &format!(
+                    "There are already {} unfinished jobs, retry later.",
+                    unfinished
+                ),
+            );
+        }
+        jobs.insert(id.clone(), Job::default());
+    }
+    tokio::spawn(run(service.clone(), id.clone(), request));
+    (StatusCode::ACCEPTED, Json(Job::default().view(&id))).into_response()
+}
+
+async fn status(State(service): State<Arc<Service>>, Path(id): Path<String>) -> Response {
+    match service.jobs().get(&id) {
+        Some(job) => Json(job.view(&id)).into_response(),
+        None => error(
+            StatusCode::NOT_FOUND,
+            "job_not_found",
+            &format!("The job '{}' does not exist or has expired.", id),
+        ),
+    }
+}
+
+async fn run(service: Arc<Service>, id: String, request: EstimationRequest) {
+    let _permit = service.permits.acquire().await;
+    service.update(&id, |job| job.status = Status::Running);
+
+    let collector = Collector::default();
+    let workloads: Vec<WorkloadDescription> = match &request.snapshot {
+        Some(snapshot) => snapshot
+            .clusters
+            .iter()
+            .map(WorkloadDescription::from)
+            .collect(),
+        None => {
+            let mut workloads = Vec::with_capacity(request.sources.len());
+            for configuration in request.sources {
+                collector.set_source(configuration.name());
+                match source::load_workload_description(
+                    &collector,
+                    &service.model,
+                    configuration,
+                    &LoadOptions {
+                        serverless: request.serverless,
+                        compatibility: request.compatibility,
+                        ..Default::default()
+                    },
+                )
+                .await
+                {
+                    Ok(Some(workload)) => workloads.push(workload),
+                    Ok(None) => collector.warn("serverless_skipped", "The cluster already runs on TiDB Serverless, set serverless to forecast its bill from the statement summary."),
+                    Err(e) => collector.error(
+                        ExitCode::of(&e).code(),
+                        &format!("The workload failed to load: {:#}", e),
+                    ),
+                }
+            }
+            workloads
+        }
+    };
+    collector.set_source(env!("CARGO_PKG_NAME").into());
+
+    let report = if workloads.is_empty() {
+        None
+    } else {
+        match calculator::estimate(&request.region, &service.model, &workloads) {
+            Ok(estimations) => Some(Report::new(
+                &request.region,
+                &service.model,
+                workloads,
+                estimations,
+                false,
+            )),
+            Err(e) => {
+                collector.error(ExitCode::of(&e).code(), &e.to_string());
+                None
+            }
+        }
+    };
+    let diagnostics = collector.into_diagnostics();
+    service.update(&id, |job| {
+        job.status = if report.is_some() {
+            Status::Succeeded
+        } else {
+            Status::Failed
+        };
+        job.diagnostics = diagnostics;
+        job.report = report;
+        job.finished_at = Some(Instant::now());
+    });
+}
+
+/// Serves the estimation API until the process is terminated. At most
+/// `concurrency` jobs are collected at the same time and at most `max_jobs`
+/// are accepted before they finish. Every request must carry the `token` as
+/// bearer token, and the sources may only connect to the `allowed_hosts`.
+pub async fn serve(
+    listen: &str,
+    concurrency: NonZeroUsize,
+    max_jobs: NonZeroUsize,
+    token: String,
+    allowed_hosts: &[String],
+    model: RequestUnitModel,
+) -> Result<()> {
+    if token.is_empty() {
+        return Err(anyhow!("The token of the API must not be empty."));
+    }
+    let service = Arc::new(Service {
+        model,
+        jobs: Mutex::new(HashMap::new()),
+        permits: Semaphore::new(concurrency.get()),
+        max_jobs: max_jobs.get(),
+        token,
+        allowed_hosts: AllowedHosts::new(allowed_hosts)?,
+    });
+    let app = Router::new()
+        .route("/estimations", post(submit))
+        .route("/estimations/:id", get(status))
+        .route_layer(middleware::from_fn_with_state(service.clone(), authorize))
+        .with_state(service);
+    let listener = tokio::net::TcpListener::bind(listen).await?;
+    axum::serve(listener, app).await?;
+    Ok(())
+}
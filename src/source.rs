@@ -1,21 +1,162 @@
 use std::cmp::{max, min};
-use std::fs::File;
-use std::io;
-use std::io::{BufReader, Write};
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
 use std::ops::Sub;
 
-use crate::output::OutputFormat;
+use crate::compatibility::{self, CompatibilityIssue};
+use crate::config;
+use crate::encoding::{self, ColumnDefinition, EncodedSize, IndexColumn, TableSchema};
+use crate::explain::Trace;
+use crate::model::RequestUnitModel;
+use crate::provider::{self, Provider};
 use anyhow::{anyhow, Result};
 use chrono::{DateTime, Duration, Utc};
 use regex::Regex;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use sqlx::{FromRow, MySql, Pool};
 
-const TARGET_REGION_SIZE: u64 = 256 * 1024 * 1024;
 const MINUTES_PER_HOUR: u64 = 60;
+pub const SECONDS_PER_HOUR: f64 = 3600f64;
+const HOURS_PER_MONTH: f64 = 730f64;
+const GIB: f64 = 1024f64 * 1024f64 * 1024f64;
+const MAX_DIGESTS: usize = 100;
+/// The most shards a group expands to, so a pattern such as
+/// `shard-[0-99999999]` fails before allocating its hosts.
+const MAX_SHARDS: usize = 1024;
+/// A quoted table name in the text of a digest, optionally qualified by its
+/// schema and followed by a quoted alias, e.g. `` `db` . `orders` AS `o` ``.
+const TABLE_REFERENCE: &str = "(?:`[^`]+`\\s*\\.\\s*)?`([^`]+)`(?:\\s+(?:(?i)AS\\s+)?`[^`]+`)?";
 
+/// Receives the errors and warnings raised while collecting a workload, each
+/// with a stable code. The collection itself never prints them, the caller
+/// decides how to surface them.
+pub trait Diagnostics: Sync {
+    fn error(&self, code: &str, message: &str);
+    fn warn(&self, code: &str, message: &str);
+}
+
+/// Discards every diagnostic.
+impl Diagnostics for () {
+    fn error(&self, _code: &str, _message: &str) {}
+    fn warn(&self, _code: &str, _message: &str) {}
+}
+
+/// How the storage of a source database is measured.
+#[derive(Debug, Clone, Copy, Default)]
+pub enum StorageMode {
+    /// The TABLE_ROWS, DATA_LENGTH and INDEX_LENGTH statistics, cheap but
+    /// possibly far off for InnoDB.
+    #[default]
+    Statistics,
+    /// The InnoDB tablespace file sizes or index page counts, with the rows
+    /// of every table counted up to the limit.
+    Exact { row_count_limit: u64 },
+}
+
+/// How the storage of a source database is measured and converted.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StorageOptions {
+    pub mode: StorageMode,
+    /// Converts the storage of MySQL to the size estimated for the TiDB
+    /// encoding, from the column and index definitions.
+    pub convert: bool,
+}
+
+/// The method the storage of a workload was measured with.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum StorageMeasurement {
+    #[default]
+    Statistics,
+    Tablespaces,
+    IndexStatistics,
+    Specification,
+}
+
+impl StorageMeasurement {
+    /// Names of the data and index sizes in the explanation.
+    fn labels(&self) -> (&'static str, &'static str) {
+        match self {
+            StorageMeasurement::Statistics => ("SUM(DATA_LENGTH)", "SUM(INDEX_LENGTH)"),
+            StorageMeasurement::Tablespaces => {
+                ("tablespace_data_in_bytes", "tablespace_index_in_bytes")
+            }
+            StorageMeasurement::IndexStatistics => {
+                ("clustered_index_in_bytes", "secondary_indexes_in_bytes")
+            }
+            StorageMeasurement::Specification => ("storage_in_bytes", "index_in_bytes"),
+        }
+    }
+}
+
+impl Display for StorageMeasurement {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            StorageMeasurement::Statistics => "table statistics",
+            StorageMeasurement::Tablespaces => "InnoDB tablespace file sizes",
+            StorageMeasurement::IndexStatistics => "InnoDB index page counts",
+            StorageMeasurement::Specification => "workload specification",
+        })
+    }
+}
+
+/// Heuristic factors applied while deriving the workload from statistics. The
+/// expected estimation uses them as is, the optimistic and pessimistic ones
+/// bracket it to produce a low and high bound.
+#[derive(Debug, Clone, Copy)]
+struct Assumptions {
+    row_size_factor: f64,
+    region_size_factor: f64,
+    storage_factor: f64,
+}
+
+const OPTIMISTIC: Assumptions = Assumptions {
+    row_size_factor: 0.8,
+    region_size_factor: 1.5,
+    storage_factor: 0.8,
+};
+
+const EXPECTED: Assumptions = Assumptions {
+    row_size_factor: 1.0,
+    region_size_factor: 1.0,
+    storage_factor: 1.0,
+};
+
+const PESSIMISTIC: Assumptions = Assumptions {
+    row_size_factor: 1.25,
+    region_size_factor: 0.5,
+    storage_factor: 1.25,
+};
+
+impl Assumptions {
+    fn scale(&self, value: u64, factor: f64) -> u64 {
+        (value as f64 * factor) as u64
+    }
+
+    fn row_size(&self, value: u64) -> u64 {
+        self.scale(value, self.row_size_factor)
+    }
+
+    /// At least a byte, so a tiny region size scaled down never divides by zero.
+    fn region_size(&self, model: &RequestUnitModel) -> u64 {
+        max(
+            self.scale(model.region_size_in_bytes, self.region_size_factor),
+            1,
+        )
+    }
+
+    fn storage(&self, value: u64) -> u64 {
+        self.scale(value, self.storage_factor)
+    }
+}
+
+/// Connection to a MySQL-compatible database whose workload is collected, as
+/// given on the command line or in a batch configuration.
 #[derive(Debug, Default, Deserialize, Serialize)]
 pub struct WorkloadSourceConfiguration {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
     #[serde(default = "default_host")]
     pub host: String,
     #[serde(default = "default_port")]
@@ -25,6 +166,96 @@ pub struct WorkloadSourceConfiguration {
     #[serde(default)]
     pub password: String,
     pub database: String,
+    /// Read replicas whose reads are added to those of the primary.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub replicas: Vec<ReplicaConfiguration>,
+    /// Hosts of the shards consolidated into a single cluster, as `host` or
+    /// `host:port` with numeric ranges such as `shard-[01-32]`. The host
+    /// above and the replicas are ignored when given.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub shards: Vec<String>,
+}
+
+/// A read replica of a source, sharing its database and, unless given, its
+/// user and password.
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+pub struct ReplicaConfiguration {
+    pub host: String,
+    #[serde(default = "default_port")]
+    pub port: u16,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+}
+
+impl std::str::FromStr for ReplicaConfiguration {
+    type Err = anyhow::Error;
+
+    /// Parses `host` or `host:port`.
+    fn from_str(value: &str) -> Result<Self> {
+        let (host, port) = split_host_port(value, default_port())?;
+        Ok(Self {
+            host,
+            port,
+            ..Default::default()
+        })
+    }
+}
+
+fn split_host_port(value: &str, default_port: u16) -> Result<(String, u16)> {
+    Ok(match value.rsplit_once(':') {
+        Some((host, port)) => (host.into(), port.parse()?),
+        None => (value.into(), default_port),
+    })
+}
+
+/// Expands the numeric ranges of a host pattern, `shard-[01-32]` giving
+/// `shard-01` to `shard-32` padded to the width of the lower bound.
+fn expand_host_pattern(pattern: &str) -> Result<Vec<String>> {
+    let range = Regex::new("\\[(\\d+)-(\\d+)\\]")?;
+    let Some(captures) = range.captures(pattern) else {
+        return Ok(vec![pattern.into()]);
+    };
+    let (Some(matched), Some(first), Some(last)) =
+        (captures.get(0), captures.get(1), captures.get(2))
+    else {
+        return Ok(vec![pattern.into()]);
+    };
+    let width = first.as_str().len();
+    let (first, last): (u64, u64) = (first.as_str().parse()?, last.as_str().parse()?);
+    if first > last {
+        return Err(anyhow!(
+            "The range of the shard pattern '{}' is empty.",
+            pattern
+        ));
+    }
+    if last - first >= MAX_SHARDS as u64 {
+        return Err(too_many_shards(pattern));
+    }
+    let mut hosts = Vec::new();
+    for index in first..=last {
+        let host = format!(
+            "{}{:0width$}{}",
+            &pattern[..matched.start()],
+            index,
+            &pattern[matched.end()..],
+            width = width
+        );
+        hosts.extend(expand_host_pattern(&host)?);
+        if hosts.len() > MAX_SHARDS {
+            return Err(too_many_shards(pattern));
+        }
+    }
+    Ok(hosts)
+}
+
+fn too_many_shards(pattern: &str) -> anyhow::Error {
+    anyhow!(
+        "The shard pattern '{}' expands to more than {} hosts.",
+        pattern,
+        MAX_SHARDS
+    )
 }
 
 fn default_host() -> String {
@@ -47,27 +278,87 @@ impl WorkloadSourceConfiguration {
         database: impl Into<String>,
     ) -> Self {
         Self {
+            name: None,
             host: host.into(),
             port,
             user: user.into(),
             password: password.into(),
             database: database.into(),
+            replicas: Vec::new(),
+            shards: Vec::new(),
         }
     }
 
     pub fn load(file: String) -> Result<Vec<Self>> {
-        let file = file.to_lowercase();
-        let reader = BufReader::new(File::open(&file)?);
-        if file.ends_with(".json") {
-            Ok(serde_json::from_reader(reader)?)
-        } else if file.ends_with(".yaml") || file.ends_with(".yml") {
-            Ok(serde_yaml::from_reader(reader)?)
-        } else {
-            Err(anyhow!(
-                "Unknown batch configuration file format. Only json and yaml are supported"
-            ))
+        config::load(&file, "batch configuration")
+    }
+
+    /// The name identifying the cluster in reports, defaults to
+    /// `host:port/database`, or the shard patterns and the database.
+    pub fn name(&self) -> String {
+        self.name.clone().unwrap_or_else(|| {
+            if self.shards.is_empty() {
+                format!("{}:{}/{}", self.host, self.port, self.database)
+            } else {
+                format!("{}/{}", self.shards.join(","), self.database)
+            }
+        })
+    }
+
+    /// The connections to every shard, with the user, password and database
+    /// of the group.
+    fn shard_configurations(&self) -> Result<Vec<Self>> {
+        let mut shards = Vec::new();
+        for pattern in &self.shards {
+            for host in expand_host_pattern(pattern)? {
+                if shards.len() == MAX_SHARDS {
+                    return Err(anyhow!(
+                        "The shard group expands to more than {} hosts.",
+                        MAX_SHARDS
+                    ));
+                }
+                let (host, port) = split_host_port(&host, self.port)?;
+                shards.push(Self::new(
+                    host,
+                    port,
+                    self.user.clone(),
+                    self.password.clone(),
+                    self.database.clone(),
+                ));
+            }
         }
+        Ok(shards)
     }
+
+    /// The hosts connected to when collecting the workload: the shards when
+    /// given, otherwise the host and its replicas.
+    pub fn hosts(&self) -> Result<Vec<String>> {
+        if !self.shards.is_empty() {
+            return Ok(self
+                .shard_configurations()?
+                .into_iter()
+                .map(|shard| shard.host)
+                .collect());
+        }
+        Ok(std::iter::once(self.host.clone())
+            .chain(self.replicas.iter().map(|replica| replica.host.clone()))
+            .collect())
+    }
+
+    /// The connection to a replica of the source.
+    fn replica(&self, replica: &ReplicaConfiguration) -> Self {
+        Self::new(
+            replica.host.clone(),
+            replica.port,
+            replica.user.clone().unwrap_or_else(|| self.user.clone()),
+            replica
+                .password
+                .clone()
+                .unwrap_or_else(|| self.password.clone()),
+            self.database.clone(),
+        )
+    }
+
     fn connection_string(&self) -> String {
         format!(
             "mysql://{}:{}@{}:{}/{}",
@@ -76,215 +367,1345 @@ impl WorkloadSourceConfiguration {
     }
 }
 
-#[derive(Debug, Default, Serialize)]
+/// A hand-written workload of a service that does not exist yet, converted
+/// with the same assumptions as a workload collected from MySQL.
+#[derive(Debug, Deserialize)]
+pub struct WorkloadSpecification {
+    pub name: String,
+    #[serde(default)]
+    pub read_queries_per_second: f64,
+    #[serde(default)]
+    pub write_queries_per_second: f64,
+    pub average_row_size_in_bytes: u64,
+    #[serde(default = "default_rows_per_query")]
+    pub rows_read_per_query: f64,
+    /// Rows returned to the client by a read query, defaults to the rows read.
+    #[serde(default)]
+    pub rows_sent_per_query: Option<f64>,
+    #[serde(default = "default_rows_per_query")]
+    pub rows_written_per_query: f64,
+    pub storage_in_gib: f64,
+    /// Overrides the egress derived from the rows sent.
+    #[serde(default)]
+    pub egress_in_gib_per_month: Option<f64>,
+}
+
+fn default_rows_per_query() -> f64 {
+    1f64
+}
+
+impl WorkloadSpecification {
+    pub fn load(file: &str) -> Result<Vec<Self>> {
+        config::load(file, "workload specification")
+    }
+
+    /// The table statistics of a database holding the specified storage.
+    fn tables(&self) -> TablesInformation {
+        let storage_in_bytes = (self.storage_in_gib * GIB) as u64;
+        TablesInformation {
+            total_rows: Some(max(
+                storage_in_bytes / max(self.average_row_size_in_bytes, 1),
+                1,
+            )),
+            total_data_in_bytes: Some(storage_in_bytes),
+            total_index_in_bytes: Some(0),
+            measurement: StorageMeasurement::Specification,
+            conversion: None,
+        }
+    }
+
+    /// The statement summary of an hour of the specified queries.
+    fn summary(&self) -> MySQLStatementsSummary {
+        let per_hour = |per_second: f64| (per_second * SECONDS_PER_HOUR) as u64;
+        let read_queries = self.read_queries_per_second * SECONDS_PER_HOUR;
+        let write_queries = self.write_queries_per_second * SECONDS_PER_HOUR;
+        let end_time = Utc::now();
+        MySQLStatementsSummary {
+            read_queries: per_hour(self.read_queries_per_second),
+            read_rows: (read_queries * self.rows_read_per_query) as u64,
+            sent_rows: (read_queries * self.rows_sent_per_query.unwrap_or(self.rows_read_per_query))
+                as u64,
+            write_queries: per_hour(self.write_queries_per_second),
+            write_rows: (write_queries * self.rows_written_per_query) as u64,
+            start_time: end_time.sub(Duration::hours(1)),
+            end_time,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
 pub struct RequestDescription {
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub requests_per_hour: Option<u64>,
     pub bytes_per_hour: u64,
+    /// The queries sending the requests, a query sends a request to every
+    /// region it reads or writes. Unknown for the sources measuring requests.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub queries_per_hour: Option<u64>,
 }
 
-#[derive(Debug, Default, Serialize)]
+#[derive(Debug, Default, Deserialize, Serialize)]
 pub struct StorageDescription {
     pub data_in_bytes: u64,
     pub index_in_bytes: u64,
+    #[serde(default)]
+    pub measurement: StorageMeasurement,
+    /// The size on the source database, data and indexes, when the storage
+    /// is converted to the size estimated for the TiDB encoding.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_in_bytes: Option<u64>,
 }
 
-#[derive(Default, Debug, Serialize)]
+/// The hourly workload and storage of a cluster, either collected from its
+/// database or converted from a specification, as input of the estimation.
+#[derive(Default, Debug, Deserialize, Serialize)]
 pub struct WorkloadDescription {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub name: String,
+    #[serde(default = "Utc::now")]
+    pub collected_at: DateTime<Utc>,
     pub read: RequestDescription,
     pub write: RequestDescription,
     pub egress: RequestDescription,
     pub storage: StorageDescription,
+    /// Request units measured by the source itself, only available on TiDB Serverless.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_units_per_hour: Option<u64>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub digests: Vec<DigestDescription>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tables: Vec<TableDescription>,
+    /// The product or managed service the source runs on.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<Provider>,
+    /// Features of the source schema TiDB Serverless does not support, none
+    /// when the schema was not scanned.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compatibility: Option<Vec<CompatibilityIssue>>,
+    /// The contribution of every shard to a workload merged from a shard group.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub shards: Vec<ShardContribution>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bounds: Option<Box<WorkloadBounds>>,
+    #[serde(skip)]
+    pub trace: Trace,
+}
+
+/// The statistics of a table, sorted by size in descending order.
+#[derive(Debug, Default, Clone, Deserialize, Serialize, FromRow)]
+pub struct TableDescription {
+    pub name: String,
+    pub rows: u64,
+    pub data_in_bytes: u64,
+    pub index_in_bytes: u64,
+}
+
+/// The share of a shard in the workload merged from a shard group.
+#[derive(Debug, Default, Clone, Deserialize, Serialize, JsonSchema)]
+pub struct ShardContribution {
+    pub name: String,
+    pub request_units_per_hour: u64,
+    pub egress_bytes_per_hour: u64,
+    pub storage_in_bytes: u64,
+}
+
+impl ShardContribution {
+    fn new(model: &RequestUnitModel, name: String, workload: &WorkloadDescription) -> Self {
+        let request_units_per_hour = workload.request_units_per_hour.unwrap_or_else(|| {
+            (model.read_request_units(
+                workload.read.requests_per_hour.unwrap_or(0) as f64,
+                workload.read.bytes_per_hour as f64,
+            ) + model.write_request_units(
+                workload.write.requests_per_hour.unwrap_or(0) as f64,
+                workload.write.bytes_per_hour as f64,
+            )) as u64
+        });
+        Self {
+            name,
+            request_units_per_hour,
+            egress_bytes_per_hour: workload.egress.bytes_per_hour,
+            storage_in_bytes: workload.storage.data_in_bytes + workload.storage.index_in_bytes,
+        }
+    }
+}
+
+/// Request units attributed to a statement digest, sorted by request units in descending order.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct DigestDescription {
+    pub digest: String,
+    pub statement_type: String,
+    pub sql: String,
+    pub executions_per_hour: u64,
+    pub request_units_per_hour: u64,
+}
+
+/// The workload derived with optimistic (low) and pessimistic (high) assumptions.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct WorkloadBounds {
+    pub low: WorkloadDescription,
+    pub high: WorkloadDescription,
 }
 
 impl WorkloadDescription {
-    fn check_summary_duration(output: OutputFormat, duration_in_minutes: u64) {
+    fn check_summary_duration(diagnostics: &dyn Diagnostics, duration_in_minutes: u64) {
         if duration_in_minutes < MINUTES_PER_HOUR {
-            output.error(&format!("The statement summary, covering only {} minute(s), is less than an hour's workload. It is highly recommended to collect at least a day's worth of data before running the estimation to prevent distortion.", duration_in_minutes));
+            diagnostics.error("summary_too_short", &format!("The statement summary, covering only {} minute(s), is less than an hour's workload. It is highly recommended to collect at least a day's worth of data before running the estimation to prevent distortion.", duration_in_minutes));
         } else if duration_in_minutes < MINUTES_PER_HOUR * 24 {
-            output.warn(&format!("The statement summary, covering only {} hour(s), is less than a full day's workload and may not reflect the full business. Consider running the tool after collecting data for a longer period to ensure accuracy.", duration_in_minutes / MINUTES_PER_HOUR));
+            diagnostics.warn("summary_too_short", &format!("The statement summary, covering only {} hour(s), is less than a full day's workload and may not reflect the full business. Consider running the tool after collecting data for a longer period to ensure accuracy.", duration_in_minutes / MINUTES_PER_HOUR));
         }
     }
+    fn with_bounds(derive: impl Fn(&Assumptions, &mut Trace) -> Self) -> Self {
+        let mut trace = Trace::new("workload");
+        let mut workload = derive(&EXPECTED, &mut trace);
+        workload.trace = trace;
+        workload.bounds = Some(Box::new(WorkloadBounds {
+            low: derive(&OPTIMISTIC, &mut Trace::default()),
+            high: derive(&PESSIMISTIC, &mut Trace::default()),
+        }));
+        workload
+    }
+
+    /// Adds the reads and egress served by a replica, including those of its
+    /// bounds. The writes and storage of a replica mirror those of the
+    /// primary and are not counted again.
+    fn add_replica(&mut self, name: &str, mut replica: WorkloadDescription) {
+        let mut trace = std::mem::take(&mut replica.trace);
+        trace.name = format!("replica {}", name);
+        self.trace.push(trace);
+        let read_requests_per_hour = self.trace.step(
+            "read.requests_per_hour",
+            "primary_read_requests_per_hour + replica_read_requests_per_hour",
+            &[
+                (
+                    "primary_read_requests_per_hour",
+                    self.read.requests_per_hour.unwrap_or(0).into(),
+                ),
+                (
+                    "replica_read_requests_per_hour",
+                    replica.read.requests_per_hour.unwrap_or(0).into(),
+                ),
+            ],
+            self.read.requests_per_hour.unwrap_or(0) + replica.read.requests_per_hour.unwrap_or(0),
+        );
+        self.read.requests_per_hour = self
+            .read
+            .requests_per_hour
+            .or(replica.read.requests_per_hour)
+            .map(|_| read_requests_per_hour);
+        self.read.queries_per_hour = self
+            .read
+            .queries_per_hour
+            .zip(replica.read.queries_per_hour)
+            .map(|(primary, replica)| primary + replica);
+        self.read.bytes_per_hour = self.trace.step(
+            "read.bytes_per_hour",
+            "primary_read_bytes_per_hour + replica_read_bytes_per_hour",
+            &[
+                (
+                    "primary_read_bytes_per_hour",
+                    self.read.bytes_per_hour.into(),
+                ),
+                (
+                    "replica_read_bytes_per_hour",
+                    replica.read.bytes_per_hour.into(),
+                ),
+            ],
+            self.read.bytes_per_hour + replica.read.bytes_per_hour,
+        );
+        self.egress.bytes_per_hour = self.trace.step(
+            "egress.bytes_per_hour",
+            "primary_egress_bytes_per_hour + replica_egress_bytes_per_hour",
+            &[
+                (
+                    "primary_egress_bytes_per_hour",
+                    self.egress.bytes_per_hour.into(),
+                ),
+                (
+                    "replica_egress_bytes_per_hour",
+                    replica.egress.bytes_per_hour.into(),
+                ),
+            ],
+            self.egress.bytes_per_hour + replica.egress.bytes_per_hour,
+        );
+        if let (Some(bounds), Some(replica_bounds)) = (&mut self.bounds, replica.bounds) {
+            bounds.low.add_replica(name, replica_bounds.low);
+            bounds.high.add_replica(name, replica_bounds.high);
+        }
+    }
+
+    /// Merges the workloads of the shards of a group, as if they ran in a
+    /// single cluster, recording the contribution of every shard.
+    fn merge_shards(model: &RequestUnitModel, shards: Vec<(String, WorkloadDescription)>) -> Self {
+        let mut trace = Trace::new("workload");
+        let mut contributions = Vec::with_capacity(shards.len());
+        let mut merged: Option<WorkloadDescription> = None;
+        for (name, mut shard) in shards {
+            contributions.push(ShardContribution::new(model, name.clone(), &shard));
+            let mut shard_trace = std::mem::take(&mut shard.trace);
+            shard_trace.name = format!("shard {}", name);
+            trace.push(shard_trace);
+            match &mut merged {
+                Some(merged) => merged.add_shard(shard),
+                None => merged = Some(shard),
+            }
+        }
+        let mut merged = merged.unwrap_or_default();
+        for (name, value) in [
+            (
+                "read.requests_per_hour",
+                merged.read.requests_per_hour.unwrap_or(0),
+            ),
+            ("read.bytes_per_hour", merged.read.bytes_per_hour),
+            (
+                "write.requests_per_hour",
+                merged.write.requests_per_hour.unwrap_or(0),
+            ),
+            ("write.bytes_per_hour", merged.write.bytes_per_hour),
+            ("egress.bytes_per_hour", merged.egress.bytes_per_hour),
+            ("storage.data_in_bytes", merged.storage.data_in_bytes),
+            ("storage.index_in_bytes", merged.storage.index_in_bytes),
+        ] {
+            trace.step(name, "sum of the shards", &[], value);
+        }
+        merged.trace = trace;
+        merged.shards = contributions;
+        merged
+    }
+
+    /// Adds the traffic, storage, statements and tables of another shard,
+    /// including those of its bounds.
+    fn add_shard(&mut self, shard: WorkloadDescription) {
+        fn sum(value: Option<u64>, other: Option<u64>) -> Option<u64> {
+            value
+                .or(other)
+                .map(|_| value.unwrap_or(0) + other.unwrap_or(0))
+        }
+        for (request, other) in [
+            (&mut self.read, &shard.read),
+            (&mut self.write, &shard.write),
+            (&mut self.egress, &shard.egress),
+        ] {
+            request.requests_per_hour = sum(request.requests_per_hour, other.requests_per_hour);
+            request.bytes_per_hour += other.bytes_per_hour;
+            request.queries_per_hour = sum(request.queries_per_hour, other.queries_per_hour);
+        }
+        self.storage.data_in_bytes += shard.storage.data_in_bytes;
+        self.storage.index_in_bytes += shard.storage.index_in_bytes;
+        self.storage.source_in_bytes =
+            sum(self.storage.source_in_bytes, shard.storage.source_in_bytes);
+        self.request_units_per_hour =
+            sum(self.request_units_per_hour, shard.request_units_per_hour);
+
+        for digest in shard.digests {
+            match self
+                .digests
+                .iter_mut()
+                .find(|merged| merged.digest == digest.digest)
+            {
+                Some(merged) => {
+                    merged.executions_per_hour += digest.executions_per_hour;
+                    merged.request_units_per_hour += digest.request_units_per_hour;
+                }
+                None => self.digests.push(digest),
+            }
+        }
+        self.digests
+            .sort_by_key(|digest| std::cmp::Reverse(digest.request_units_per_hour));
+        self.digests.truncate(MAX_DIGESTS);
+
+        for table in shard.tables {
+            match self
+                .tables
+                .iter_mut()
+                .find(|merged| merged.name == table.name)
+            {
+                Some(merged) => {
+                    merged.rows += table.rows;
+                    merged.data_in_bytes += table.data_in_bytes;
+                    merged.index_in_bytes += table.index_in_bytes;
+                }
+                None => self.tables.push(table),
+            }
+        }
+        self.tables
+            .sort_by_key(|table| std::cmp::Reverse(table.data_in_bytes + table.index_in_bytes));
+
+        if let Some(issues) = shard.compatibility {
+            let compatibility = self.compatibility.get_or_insert_with(Vec::new);
+            for issue in issues {
+                match compatibility
+                    .iter_mut()
+                    .find(|merged| merged.code == issue.code)
+                {
+                    Some(merged) => {
+                        for object in issue.objects {
+                            if !merged.objects.contains(&object) {
+                                merged.objects.push(object);
+                            }
+                        }
+                    }
+                    None => compatibility.push(issue),
+                }
+            }
+        }
+
+        if let (Some(bounds), Some(shard_bounds)) = (&mut self.bounds, shard.bounds) {
+            bounds.low.add_shard(shard_bounds.low);
+            bounds.high.add_shard(shard_bounds.high);
+        }
+    }
+
+    fn storage(
+        tables: &TablesInformation,
+        assumptions: &Assumptions,
+        trace: &mut Trace,
+    ) -> StorageDescription {
+        let mut data_in_bytes = tables.total_data_in_bytes.unwrap_or(0);
+        let mut index_in_bytes = tables.total_index_in_bytes.unwrap_or(0);
+        let (mut data_label, mut index_label) = tables.measurement.labels();
+        let source_in_bytes = tables.conversion.map(|conversion| {
+            let source_in_bytes = data_in_bytes + index_in_bytes;
+            data_in_bytes = trace.step(
+                "storage.tidb_data_in_bytes",
+                &format!("{} * tidb_data_size / mysql_data_size", data_label),
+                &[
+                    (data_label, data_in_bytes.into()),
+                    ("tidb_data_size", conversion.tidb_data_in_bytes.into()),
+                    ("mysql_data_size", conversion.mysql_data_in_bytes.into()),
+                ],
+                (data_in_bytes as f64 * conversion.data_ratio()) as u64,
+            );
+            index_in_bytes = trace.step(
+                "storage.tidb_index_in_bytes",
+                &format!("{} * tidb_index_size / mysql_index_size", index_label),
+                &[
+                    (index_label, index_in_bytes.into()),
+                    ("tidb_index_size", conversion.tidb_index_in_bytes.into()),
+                    ("mysql_index_size", conversion.mysql_index_in_bytes.into()),
+                ],
+                (index_in_bytes as f64 * conversion.index_ratio()) as u64,
+            );
+            (data_label, index_label) = ("tidb_data_in_bytes", "tidb_index_in_bytes");
+            source_in_bytes
+        });
+        StorageDescription {
+            data_in_bytes: trace.step(
+                "storage.data_in_bytes",
+                &format!("{} * storage_factor", data_label),
+                &[
+                    (data_label, data_in_bytes.into()),
+                    ("storage_factor", assumptions.storage_factor.into()),
+                ],
+                assumptions.storage(data_in_bytes),
+            ),
+            index_in_bytes: trace.step(
+                "storage.index_in_bytes",
+                &format!("{} * storage_factor", index_label),
+                &[
+                    (index_label, index_in_bytes.into()),
+                    ("storage_factor", assumptions.storage_factor.into()),
+                ],
+                assumptions.storage(index_in_bytes),
+            ),
+            measurement: tables.measurement,
+            source_in_bytes,
+        }
+    }
+
+    fn average_row_size(
+        tables: &TablesInformation,
+        assumptions: &Assumptions,
+        trace: &mut Trace,
+    ) -> u64 {
+        let total_storage_in_bytes = max(
+            tables.total_index_in_bytes.unwrap_or(0) + tables.total_data_in_bytes.unwrap_or(0),
+            1,
+        );
+        let total_rows = max(tables.total_rows.unwrap_or(0), 1);
+        trace.step(
+            "average_row_size_in_bytes",
+            "total_storage_in_bytes / total_rows * row_size_factor",
+            &[
+                ("total_storage_in_bytes", total_storage_in_bytes.into()),
+                ("total_rows", total_rows.into()),
+                ("row_size_factor", assumptions.row_size_factor.into()),
+            ],
+            assumptions.row_size(total_storage_in_bytes / total_rows),
+        )
+    }
+
     fn mysql(
-        output: OutputFormat,
+        diagnostics: &dyn Diagnostics,
+        model: &RequestUnitModel,
         tables: TablesInformation,
         summary: MySQLStatementsSummary,
     ) -> Self {
         let duration_in_minutes =
             max(summary.end_time.sub(summary.start_time).num_minutes(), 1) as u64;
-        Self::check_summary_duration(output, duration_in_minutes);
+        Self::check_summary_duration(diagnostics, duration_in_minutes);
+        let mut workload = Self::with_bounds(|assumptions, trace| {
+            Self::mysql_with(
+                assumptions,
+                trace,
+                model,
+                &tables,
+                &summary,
+                duration_in_minutes,
+            )
+        });
+        workload.tables = summary.tables;
+        workload
+    }
+
+    fn specification(model: &RequestUnitModel, specification: &WorkloadSpecification) -> Self {
+        let tables = specification.tables();
+        let summary = specification.summary();
+        let mut workload = Self::with_bounds(|assumptions, trace| {
+            let mut workload = Self::mysql_with(
+                assumptions,
+                trace,
+                model,
+                &tables,
+                &summary,
+                MINUTES_PER_HOUR,
+            );
+            if let Some(egress_in_gib_per_month) = specification.egress_in_gib_per_month {
+                workload.egress.bytes_per_hour = trace.step(
+                    "egress.bytes_per_hour",
+                    "egress_in_gib_per_month * 1GiB / 730",
+                    &[("egress_in_gib_per_month", egress_in_gib_per_month.into())],
+                    (egress_in_gib_per_month * GIB / HOURS_PER_MONTH) as u64,
+                );
+            }
+            workload
+        });
+        workload.name = specification.name.clone();
+        workload.collected_at = Utc::now();
+        workload
+    }
+
+    fn mysql_with(
+        assumptions: &Assumptions,
+        trace: &mut Trace,
+        model: &RequestUnitModel,
+        tables: &TablesInformation,
+        summary: &MySQLStatementsSummary,
+        duration_in_minutes: u64,
+    ) -> Self {
         let total_storage_in_bytes = max(
             tables.total_index_in_bytes.unwrap_or(0) + tables.total_data_in_bytes.unwrap_or(0),
             1,
         );
-        let average_row_size_in_bytes =
-            total_storage_in_bytes / max(tables.total_rows.unwrap_or(0), 1);
-        let estimated_number_of_regions = total_storage_in_bytes / TARGET_REGION_SIZE;
+        let average_row_size_in_bytes = Self::average_row_size(tables, assumptions, trace);
+        let estimated_number_of_regions = trace.step(
+            "estimated_number_of_regions",
+            "total_storage_in_bytes / region_size_in_bytes",
+            &[
+                ("total_storage_in_bytes", total_storage_in_bytes.into()),
+                (
+                    "region_size_in_bytes",
+                    assumptions.region_size(model).into(),
+                ),
+            ],
+            total_storage_in_bytes / assumptions.region_size(model),
+        );
 
-        let read_bytes_per_hour =
-            MINUTES_PER_HOUR * average_row_size_in_bytes * summary.read_rows / duration_in_minutes;
-        let read_queries_per_hour = max(
-            MINUTES_PER_HOUR * summary.read_queries / duration_in_minutes,
-            1,
+        let read_bytes_per_hour = trace.step(
+            "read_bytes_per_hour",
+            "60 * average_row_size_in_bytes * rows_examined / duration_in_minutes",
+            &[
+                (
+                    "average_row_size_in_bytes",
+                    average_row_size_in_bytes.into(),
+                ),
+                ("rows_examined", summary.read_rows.into()),
+                ("duration_in_minutes", duration_in_minutes.into()),
+            ],
+            MINUTES_PER_HOUR * average_row_size_in_bytes * summary.read_rows / duration_in_minutes,
         );
-        let read_bytes_per_request = read_bytes_per_hour / read_queries_per_hour;
-        let read_regions_per_query = max(
-            read_bytes_per_request * estimated_number_of_regions / total_storage_in_bytes,
-            1,
+        let read_queries_per_hour = trace.step(
+            "read_queries_per_hour",
+            "max(60 * read_queries / duration_in_minutes, 1)",
+            &[
+                ("read_queries", summary.read_queries.into()),
+                ("duration_in_minutes", duration_in_minutes.into()),
+            ],
+            max(
+                MINUTES_PER_HOUR * summary.read_queries / duration_in_minutes,
+                1,
+            ),
         );
+        let read_bytes_per_request = read_bytes_per_hour / read_queries_per_hour;
+        let read_regions_per_query = match summary.regions(
+            false,
+            assumptions,
+            assumptions.region_size(model),
+            average_row_size_in_bytes,
+        ) {
+            Some(read_regions) => trace.step(
+                "read_regions_per_query",
+                "max(read_regions / read_queries, 1), summing the regions of the tables referenced by every digest",
+                &[
+                    ("read_regions", read_regions.into()),
+                    ("read_queries", summary.read_queries.into()),
+                ],
+                max(
+                    (read_regions as f64 / max(summary.read_queries, 1) as f64).round() as u64,
+                    1,
+                ),
+            ),
+            None => trace.step(
+                "read_regions_per_query",
+                "max(read_bytes_per_query * estimated_number_of_regions / total_storage_in_bytes, 1)",
+                &[
+                    ("read_bytes_per_query", read_bytes_per_request.into()),
+                    (
+                        "estimated_number_of_regions",
+                        estimated_number_of_regions.into(),
+                    ),
+                    ("total_storage_in_bytes", total_storage_in_bytes.into()),
+                ],
+                max(
+                    read_bytes_per_request * estimated_number_of_regions / total_storage_in_bytes,
+                    1,
+                ),
+            ),
+        };
 
-        let write_bytes_per_hour =
-            MINUTES_PER_HOUR * average_row_size_in_bytes * summary.write_rows / duration_in_minutes;
-        let write_queries_per_hour = max(
-            MINUTES_PER_HOUR * summary.write_queries / duration_in_minutes,
-            1,
+        let write_bytes_per_hour = trace.step(
+            "write_bytes_per_hour",
+            "60 * average_row_size_in_bytes * rows_affected / duration_in_minutes",
+            &[
+                (
+                    "average_row_size_in_bytes",
+                    average_row_size_in_bytes.into(),
+                ),
+                ("rows_affected", summary.write_rows.into()),
+                ("duration_in_minutes", duration_in_minutes.into()),
+            ],
+            MINUTES_PER_HOUR * average_row_size_in_bytes * summary.write_rows / duration_in_minutes,
         );
-        let write_bytes_per_query = write_bytes_per_hour / write_queries_per_hour;
-        let write_regions_per_query = max(
-            write_bytes_per_query * estimated_number_of_regions / total_storage_in_bytes,
-            1,
+        let write_queries_per_hour = trace.step(
+            "write_queries_per_hour",
+            "max(60 * write_queries / duration_in_minutes, 1)",
+            &[
+                ("write_queries", summary.write_queries.into()),
+                ("duration_in_minutes", duration_in_minutes.into()),
+            ],
+            max(
+                MINUTES_PER_HOUR * summary.write_queries / duration_in_minutes,
+                1,
+            ),
         );
+        let write_bytes_per_query = write_bytes_per_hour / write_queries_per_hour;
+        let write_regions_per_query = match summary.regions(
+            true,
+            assumptions,
+            assumptions.region_size(model),
+            average_row_size_in_bytes,
+        ) {
+            Some(write_regions) => trace.step(
+                "write_regions_per_query",
+                "max(write_regions / write_queries, 1), summing the regions of the tables referenced by every digest",
+                &[
+                    ("write_regions", write_regions.into()),
+                    ("write_queries", summary.write_queries.into()),
+                ],
+                max(
+                    (write_regions as f64 / max(summary.write_queries, 1) as f64).round() as u64,
+                    1,
+                ),
+            ),
+            None => trace.step(
+                "write_regions_per_query",
+                "max(write_bytes_per_query * estimated_number_of_regions / total_storage_in_bytes, 1)",
+                &[
+                    ("write_bytes_per_query", write_bytes_per_query.into()),
+                    (
+                        "estimated_number_of_regions",
+                        estimated_number_of_regions.into(),
+                    ),
+                    ("total_storage_in_bytes", total_storage_in_bytes.into()),
+                ],
+                max(
+                    write_bytes_per_query * estimated_number_of_regions / total_storage_in_bytes,
+                    1,
+                ),
+            ),
+        };
 
         WorkloadDescription {
             read: RequestDescription {
-                requests_per_hour: (read_queries_per_hour * read_regions_per_query).into(),
+                requests_per_hour: trace
+                    .step(
+                        "read.requests_per_hour",
+                        "read_queries_per_hour * read_regions_per_query",
+                        &[
+                            ("read_queries_per_hour", read_queries_per_hour.into()),
+                            ("read_regions_per_query", read_regions_per_query.into()),
+                        ],
+                        read_queries_per_hour * read_regions_per_query,
+                    )
+                    .into(),
                 bytes_per_hour: read_bytes_per_hour,
+                queries_per_hour: Some(read_queries_per_hour),
             },
             write: RequestDescription {
-                requests_per_hour: (write_queries_per_hour * write_regions_per_query).into(),
+                requests_per_hour: trace
+                    .step(
+                        "write.requests_per_hour",
+                        "write_queries_per_hour * write_regions_per_query",
+                        &[
+                            ("write_queries_per_hour", write_queries_per_hour.into()),
+                            ("write_regions_per_query", write_regions_per_query.into()),
+                        ],
+                        write_queries_per_hour * write_regions_per_query,
+                    )
+                    .into(),
                 bytes_per_hour: write_bytes_per_hour,
+                queries_per_hour: Some(write_queries_per_hour),
             },
             egress: RequestDescription {
-                bytes_per_hour: MINUTES_PER_HOUR * average_row_size_in_bytes * summary.sent_rows
-                    / duration_in_minutes,
+                bytes_per_hour: trace.step(
+                    "egress.bytes_per_hour",
+                    "60 * average_row_size_in_bytes * rows_sent / duration_in_minutes",
+                    &[
+                        (
+                            "average_row_size_in_bytes",
+                            average_row_size_in_bytes.into(),
+                        ),
+                        ("rows_sent", summary.sent_rows.into()),
+                        ("duration_in_minutes", duration_in_minutes.into()),
+                    ],
+                    MINUTES_PER_HOUR * average_row_size_in_bytes * summary.sent_rows
+                        / duration_in_minutes,
+                ),
                 ..Default::default()
             },
-            storage: StorageDescription {
-                data_in_bytes: tables.total_data_in_bytes.unwrap_or(0),
-                index_in_bytes: tables.total_index_in_bytes.unwrap_or(0),
-            },
+            storage: Self::storage(tables, assumptions, trace),
+            ..Default::default()
         }
     }
 
     fn tidb(
-        output: OutputFormat,
+        diagnostics: &dyn Diagnostics,
         tables: TablesInformation,
         summary: Option<TiDBStatementsSummary>,
         metrics: TiDBSystemMetrics,
     ) -> Self {
-        let (write_bytes_per_hour, sent_bytes_per_hour) = match summary {
+        let duration_in_minutes = match &summary {
             Some(summary) => {
                 let duration_in_minutes =
                     max(summary.end_time.sub(summary.start_time).num_minutes(), 1) as u64;
-                Self::check_summary_duration(output, duration_in_minutes);
-                let average_row_size_in_bytes = (tables.total_index_in_bytes.unwrap_or(0)
-                    + tables.total_data_in_bytes.unwrap_or(0))
-                    / max(1, tables.total_rows.unwrap_or(0));
-                (
-                    MINUTES_PER_HOUR * summary.write_bytes / duration_in_minutes,
-                    MINUTES_PER_HOUR * summary.sent_rows * average_row_size_in_bytes
-                        / duration_in_minutes,
-                )
+                Self::check_summary_duration(diagnostics, duration_in_minutes);
+                duration_in_minutes
             }
             None => {
-                output.warn("The 'Statement Summary Tables' are disabled; when they are available, estimations can be more accurate.");
-                output.warn("For detailed instruction, visit https://docs.pingcap.com/tidb/stable/statement-summary-tables#parameter-configuration");
-                (metrics.write_bytes_per_hour, 0)
+                diagnostics.warn("statement_summary_disabled", "The 'Statement Summary Tables' are disabled; when they are available, estimations can be more accurate.");
+                diagnostics.warn("statement_summary_disabled", "For detailed instruction, visit https://docs.pingcap.com/tidb/stable/statement-summary-tables#parameter-configuration");
+                0
             }
         };
+        Self::with_bounds(|assumptions, trace| {
+            Self::tidb_with(
+                assumptions,
+                trace,
+                &tables,
+                summary.as_ref(),
+                &metrics,
+                duration_in_minutes,
+            )
+        })
+    }
+
+    fn tidb_with(
+        assumptions: &Assumptions,
+        trace: &mut Trace,
+        tables: &TablesInformation,
+        summary: Option<&TiDBStatementsSummary>,
+        metrics: &TiDBSystemMetrics,
+        duration_in_minutes: u64,
+    ) -> Self {
+        let (write_bytes_per_hour, sent_bytes_per_hour) = match summary {
+            Some(summary) => {
+                let average_row_size_in_bytes = Self::average_row_size(tables, assumptions, trace);
+                (
+                    trace.step(
+                        "write.bytes_per_hour",
+                        "60 * write_bytes / duration_in_minutes",
+                        &[
+                            ("write_bytes", summary.write_bytes.into()),
+                            ("duration_in_minutes", duration_in_minutes.into()),
+                        ],
+                        MINUTES_PER_HOUR * summary.write_bytes / duration_in_minutes,
+                    ),
+                    trace.step(
+                        "egress.bytes_per_hour",
+                        "60 * rows_sent * average_row_size_in_bytes / duration_in_minutes",
+                        &[
+                            ("rows_sent", summary.sent_rows.into()),
+                            (
+                                "average_row_size_in_bytes",
+                                average_row_size_in_bytes.into(),
+                            ),
+                            ("duration_in_minutes", duration_in_minutes.into()),
+                        ],
+                        MINUTES_PER_HOUR * summary.sent_rows * average_row_size_in_bytes
+                            / duration_in_minutes,
+                    ),
+                )
+            }
+            None => (
+                trace.step(
+                    "write.bytes_per_hour",
+                    "tidb_kv_write_total_size / hours",
+                    &[],
+                    metrics.write_bytes_per_hour,
+                ),
+                0,
+            ),
+        };
         WorkloadDescription {
             read: RequestDescription {
-                requests_per_hour: metrics.read_requests_per_hour.into(),
-                bytes_per_hour: metrics.read_bytes_per_hour,
+                requests_per_hour: trace
+                    .step(
+                        "read.requests_per_hour",
+                        "tidb_kv_request_total_count{type!~Prewrite|Commit} / hours",
+                        &[],
+                        metrics.read_requests_per_hour,
+                    )
+                    .into(),
+                bytes_per_hour: trace.step(
+                    "read.bytes_per_hour",
+                    "tikv_cop_total_rocksdb_perf_statistics{metric=~get_read_bytes|iter_read_bytes} / hours",
+                    &[],
+                    metrics.read_bytes_per_hour,
+                ),
+                ..Default::default()
             },
             write: RequestDescription {
-                requests_per_hour: metrics.write_requests_per_hour.into(),
+                requests_per_hour: trace
+                    .step(
+                        "write.requests_per_hour",
+                        "tidb_kv_request_total_count{type=~Prewrite|Commit} / hours",
+                        &[],
+                        metrics.write_requests_per_hour,
+                    )
+                    .into(),
                 bytes_per_hour: write_bytes_per_hour,
+                ..Default::default()
             },
             egress: RequestDescription {
                 bytes_per_hour: sent_bytes_per_hour,
                 ..Default::default()
             },
-            storage: StorageDescription {
-                data_in_bytes: tables.total_data_in_bytes.unwrap_or(0),
-                index_in_bytes: tables.total_index_in_bytes.unwrap_or(0),
+            storage: Self::storage(tables, assumptions, trace),
+            ..Default::default()
+        }
+    }
+
+    fn serverless(
+        diagnostics: &dyn Diagnostics,
+        model: &RequestUnitModel,
+        tables: TablesInformation,
+        summary: ServerlessStatementsSummary,
+    ) -> Self {
+        let duration_in_minutes = max(
+            summary
+                .statements
+                .end_time
+                .sub(summary.statements.start_time)
+                .num_minutes(),
+            1,
+        ) as u64;
+        Self::check_summary_duration(diagnostics, duration_in_minutes);
+        if summary.request_units.is_none() {
+            diagnostics.warn("request_units_estimated", "The statement summary does not expose request units; they are estimated with the request unit model instead.");
+        }
+        let mut workload = Self::with_bounds(|assumptions, trace| {
+            Self::serverless_with(assumptions, trace, &tables, &summary, duration_in_minutes)
+        });
+        let average_row_size_in_bytes =
+            Self::average_row_size(&tables, &EXPECTED, &mut Trace::default());
+        workload.digests = Self::digests(
+            model,
+            &summary,
+            average_row_size_in_bytes,
+            duration_in_minutes,
+        );
+        workload
+    }
+
+    fn serverless_with(
+        assumptions: &Assumptions,
+        trace: &mut Trace,
+        tables: &TablesInformation,
+        summary: &ServerlessStatementsSummary,
+        duration_in_minutes: u64,
+    ) -> Self {
+        let statements = &summary.statements;
+        let average_row_size_in_bytes = Self::average_row_size(tables, assumptions, trace);
+        WorkloadDescription {
+            read: RequestDescription {
+                requests_per_hour: trace
+                    .step(
+                        "read.requests_per_hour",
+                        "60 * read_queries / duration_in_minutes",
+                        &[
+                            ("read_queries", statements.read_queries.into()),
+                            ("duration_in_minutes", duration_in_minutes.into()),
+                        ],
+                        MINUTES_PER_HOUR * statements.read_queries / duration_in_minutes,
+                    )
+                    .into(),
+                bytes_per_hour: trace.step(
+                    "read.bytes_per_hour",
+                    "60 * processed_keys * average_row_size_in_bytes / duration_in_minutes",
+                    &[
+                        ("processed_keys", statements.read_rows.into()),
+                        ("average_row_size_in_bytes", average_row_size_in_bytes.into()),
+                        ("duration_in_minutes", duration_in_minutes.into()),
+                    ],
+                    MINUTES_PER_HOUR * statements.read_rows * average_row_size_in_bytes
+                        / duration_in_minutes,
+                ),
+                ..Default::default()
+            },
+            write: RequestDescription {
+                requests_per_hour: trace
+                    .step(
+                        "write.requests_per_hour",
+                        "60 * write_queries / duration_in_minutes",
+                        &[
+                            ("write_queries", statements.write_queries.into()),
+                            ("duration_in_minutes", duration_in_minutes.into()),
+                        ],
+                        MINUTES_PER_HOUR * statements.write_queries / duration_in_minutes,
+                    )
+                    .into(),
+                bytes_per_hour: trace.step(
+                    "write.bytes_per_hour",
+                    "60 * write_bytes / duration_in_minutes",
+                    &[
+                        ("write_bytes", statements.write_bytes.into()),
+                        ("duration_in_minutes", duration_in_minutes.into()),
+                    ],
+                    MINUTES_PER_HOUR * statements.write_bytes / duration_in_minutes,
+                ),
+                ..Default::default()
+            },
+            egress: RequestDescription {
+                bytes_per_hour: trace.step(
+                    "egress.bytes_per_hour",
+                    "60 * rows_sent * average_row_size_in_bytes / duration_in_minutes",
+                    &[
+                        ("rows_sent", statements.sent_rows.into()),
+                        ("average_row_size_in_bytes", average_row_size_in_bytes.into()),
+                        ("duration_in_minutes", duration_in_minutes.into()),
+                    ],
+                    MINUTES_PER_HOUR * statements.sent_rows * average_row_size_in_bytes
+                        / duration_in_minutes,
+                ),
+                ..Default::default()
             },
+            storage: Self::storage(tables, assumptions, trace),
+            request_units_per_hour: summary.request_units.map(|request_units| {
+                trace.step(
+                    "request_units_per_hour",
+                    "60 * (AVG_REQUEST_UNIT_READ + AVG_REQUEST_UNIT_WRITE) * EXEC_COUNT / duration_in_minutes",
+                    &[
+                        ("request_units", request_units.into()),
+                        ("duration_in_minutes", duration_in_minutes.into()),
+                    ],
+                    (MINUTES_PER_HOUR as f64 * request_units / duration_in_minutes as f64) as u64,
+                )
+            }),
+            ..Default::default()
         }
     }
+
+    fn digests(
+        model: &RequestUnitModel,
+        summary: &ServerlessStatementsSummary,
+        average_row_size_in_bytes: u64,
+        duration_in_minutes: u64,
+    ) -> Vec<DigestDescription> {
+        let mut digests: Vec<DigestDescription> = summary
+            .digests
+            .iter()
+            .map(|digest| {
+                let request_units = match summary.request_units {
+                    Some(_) => digest.request_units,
+                    None if digest.statement.is_write() => model.write_request_units(
+                        digest.statement.count as f64,
+                        (digest.statement.avg_write_bytes * digest.statement.count) as f64,
+                    ),
+                    None => model.read_request_units(
+                        digest.statement.count as f64,
+                        (digest.statement.avg_processed_keys
+                            * digest.statement.count
+                            * average_row_size_in_bytes) as f64,
+                    ),
+                };
+                DigestDescription {
+                    digest: digest.digest.clone(),
+                    statement_type: digest.statement.statement_type.clone(),
+                    sql: digest.sql.clone(),
+                    executions_per_hour: MINUTES_PER_HOUR * digest.statement.count
+                        / duration_in_minutes,
+                    request_units_per_hour: (MINUTES_PER_HOUR as f64 * request_units
+                        / duration_in_minutes as f64)
+                        as u64,
+                }
+            })
+            .collect();
+        digests.sort_by_key(|digest| std::cmp::Reverse(digest.request_units_per_hour));
+        digests.truncate(MAX_DIGESTS);
+        digests
+    }
+}
+
+/// Safety controls of the ANALYZE run before collecting the workload. Only
+/// tables with missing or stale statistics are analyzed.
+#[derive(Debug, Clone, Default)]
+pub struct AnalyzeOptions {
+    /// Tables larger than this, data and indexes, are skipped.
+    pub max_table_size_in_bytes: Option<u64>,
+    /// Pause between two tables to spread the load on the source.
+    pub pause: std::time::Duration,
+    /// Only report the tables that would be analyzed.
+    pub dry_run: bool,
 }
 
-async fn run_analyze(output: OutputFormat, pool: &Pool<MySql>) -> Result<()> {
-    let tables: Vec<String> = sqlx::query_as("SHOW FULL TABLES WHERE Table_type = 'BASE TABLE'")
+#[derive(FromRow, Debug)]
+struct AnalyzeCandidate {
+    name: String,
+    size_in_bytes: u64,
+    stale: i64,
+}
+
+/// The base tables with their size, stale when InnoDB has no persistent
+/// statistics for them or the table was updated after they were computed.
+/// Without `mysql.innodb_table_stats`, as on TiDB, every table is stale.
+async fn read_analyze_candidates(
+    diagnostics: &dyn Diagnostics,
+    pool: &Pool<MySql>,
+    database: &str,
+) -> Result<Vec<AnalyzeCandidate>> {
+    let candidates = sqlx::query_as("SELECT t.TABLE_NAME AS name, CAST(IFNULL(t.DATA_LENGTH, 0) + IFNULL(t.INDEX_LENGTH, 0) AS UNSIGNED) AS size_in_bytes, CAST(s.last_update IS NULL OR IFNULL(t.UPDATE_TIME > s.last_update, FALSE) AS SIGNED) AS stale FROM information_schema.TABLES t LEFT JOIN mysql.innodb_table_stats s ON s.database_name = t.TABLE_SCHEMA AND s.table_name = t.TABLE_NAME WHERE t.TABLE_SCHEMA=? AND t.TABLE_TYPE='BASE TABLE' ORDER BY t.TABLE_NAME")
+        .bind(database)
         .fetch_all(pool)
-        .await?
-        .into_iter()
-        .map(|v: (String, String)| v.0)
-        .collect();
-    for table in tables {
-        output.warn(&format!("Analyzing table `{}`. Press CTRL+C to terminate if you notice unexpected performance impacts on the production system.", table));
-        sqlx::query(&format!("ANALYZE TABLE `{}`", table))
-            .execute(pool)
-            .await?;
+        .await;
+    match candidates {
+        Ok(candidates) => Ok(candidates),
+        Err(_) => {
+            diagnostics.warn(
+                "analyze_statistics_unknown",
+                "The age of the table statistics is unknown, every table is considered stale.",
+            );
+            Ok(sqlx::query_as("SELECT TABLE_NAME AS name, CAST(IFNULL(DATA_LENGTH, 0) + IFNULL(INDEX_LENGTH, 0) AS UNSIGNED) AS size_in_bytes, CAST(1 AS SIGNED) AS stale FROM information_schema.TABLES WHERE TABLE_SCHEMA=? AND TABLE_TYPE='BASE TABLE' ORDER BY TABLE_NAME")
+                .bind(database)
+                .fetch_all(pool)
+                .await?)
+        }
     }
-    Ok(())
 }
 
-async fn confirm_and_run_analyze(output: OutputFormat, pool: &Pool<MySql>) -> Result<()> {
-    loop {
-        output.warn("Running ANALYZE on the production system may affect ongoing queries. Do you want to proceed? (yes/no): ");
-        io::stdout().flush().unwrap_or(());
-        let mut confirmation = String::new();
-        io::stdin().read_line(&mut confirmation)?;
-        match confirmation.trim().to_lowercase().as_str() {
-            "yes" => break,
-            "no" => return Ok(()),
-            _ => continue,
+async fn run_analyze(
+    diagnostics: &dyn Diagnostics,
+    pool: &Pool<MySql>,
+    database: &str,
+    options: &AnalyzeOptions,
+) -> Result<()> {
+    let mut analyzed = 0;
+    for candidate in read_analyze_candidates(diagnostics, pool, database).await? {
+        if candidate.stale == 0 {
+            continue;
+        }
+        if options
+            .max_table_size_in_bytes
+            .is_some_and(|max_size| candidate.size_in_bytes > max_size)
+        {
+            diagnostics.warn("analyze_skipped", &format!("Skipping table `{}` of {} bytes, which is larger than the maximum table size to analyze.", candidate.name, candidate.size_in_bytes));
+            continue;
         }
+        if options.dry_run {
+            diagnostics.warn(
+                "analyze_dry_run",
+                &format!(
+                    "Table `{}` of {} bytes has stale statistics and would be analyzed.",
+                    candidate.name, candidate.size_in_bytes
+                ),
+            );
+            continue;
+        }
+        if analyzed > 0 && !options.pause.is_zero() {
+            tokio::time::sleep(options.pause).await;
+        }
+        diagnostics.warn("analyze_table", &format!("Analyzing table `{}`. Press CTRL+C to terminate if you notice unexpected performance impacts on the production system.", candidate.name));
+        sqlx::query(&format!(
+            "ANALYZE TABLE {}",
+            quote_identifier(&candidate.name)
+        ))
+        .execute(pool)
+        .await?;
+        analyzed += 1;
     }
-    run_analyze(output, pool).await
+    Ok(())
+}
+
+/// Converts the hand-written workload specifications of a json or yaml file.
+pub fn load_workload_specifications(
+    model: &RequestUnitModel,
+    file: &str,
+) -> Result<Vec<WorkloadDescription>> {
+    Ok(WorkloadSpecification::load(file)?
+        .iter()
+        .map(|specification| WorkloadDescription::specification(model, specification))
+        .collect())
+}
+
+/// How a workload is loaded from its source database, the default collects
+/// it as it is without running ANALYZE.
+#[derive(Debug, Clone, Default)]
+pub struct LoadOptions {
+    /// Runs ANALYZE on the tables with stale statistics first.
+    pub analyze: Option<AnalyzeOptions>,
+    pub storage: StorageOptions,
+    /// Estimates a TiDB Serverless cluster from its statement summary
+    /// instead of skipping it.
+    pub serverless: bool,
+    /// Scans the schema for the features TiDB Serverless does not support.
+    pub compatibility: bool,
 }
 
+/// Connects to the source database and derives its workload, running ANALYZE
+/// on the tables with stale statistics first and scanning the schema for
+/// compatibility issues when asked to. The workloads of a shard group are
+/// merged into one. Returns none for a TiDB Serverless cluster unless
+/// `serverless` is set.
 pub async fn load_workload_description(
-    output: OutputFormat,
+    diagnostics: &dyn Diagnostics,
+    model: &RequestUnitModel,
     config: WorkloadSourceConfiguration,
-    analyze_before_start: bool,
+    options: &LoadOptions,
+) -> Result<Option<WorkloadDescription>> {
+    if config.shards.is_empty() {
+        return load_source_workload_description(diagnostics, model, &config, options).await;
+    }
+    let mut shards = Vec::new();
+    for shard in config.shard_configurations()? {
+        let Some(workload) = load_source_workload_description(diagnostics, model, &shard, options)
+            .await
+            .map_err(|e| e.context(format!("The shard {} failed to load", shard.name())))?
+        else {
+            return Ok(None);
+        };
+        shards.push((shard.name(), workload));
+    }
+    let mut workload = WorkloadDescription::merge_shards(model, shards);
+    workload.name = config.name();
+    workload.collected_at = Utc::now();
+    Ok(Some(workload))
+}
+
+async fn load_source_workload_description(
+    diagnostics: &dyn Diagnostics,
+    model: &RequestUnitModel,
+    config: &WorkloadSourceConfiguration,
+    options: &LoadOptions,
 ) -> Result<Option<WorkloadDescription>> {
-    let pool = sqlx::MySqlPool::connect(&config.connection_string()).await?;
+    let pool = sqlx::MySqlPool::connect(&config.connection_string())
+        .await
+        .map_err(SourceError::Connection)?;
 
-    if analyze_before_start {
-        confirm_and_run_analyze(output, &pool).await?
+    if let Some(analyze) = &options.analyze {
+        run_analyze(diagnostics, &pool, &config.database, analyze).await?
     }
 
-    let tables = read_tables_information(&pool, &config.database).await?;
-    if is_tidb(&pool).await? {
-        if is_tidb_serverless(&pool).await? {
-            Ok(None)
+    let provider = provider::detect(&pool).await?;
+    if !provider.is_tidb() {
+        check_primary(diagnostics, &pool, provider).await?;
+    }
+    let Some(mut workload) = read_workload_description(
+        diagnostics,
+        model,
+        &pool,
+        config,
+        provider,
+        options.storage,
+        options.serverless,
+    )
+    .await?
+    else {
+        return Ok(None);
+    };
+    for replica in &config.replicas {
+        let replica = config.replica(replica);
+        let replica_workload =
+            read_replica_workload_description(diagnostics, model, &replica, options.serverless)
+                .await?;
+        workload.add_replica(&replica.name(), replica_workload);
+    }
+    workload.name = config.name();
+    workload.collected_at = Utc::now();
+    workload.provider = Some(provider);
+    if workload.tables.is_empty() {
+        workload.tables = read_table_descriptions(&pool, &config.database).await?;
+    }
+    if options.compatibility && !provider.is_tidb() {
+        workload.compatibility = Some(compatibility::scan(&pool, &config.database).await?);
+    }
+    Ok(Some(workload))
+}
+
+/// Connects to a replica and derives its workload, only its reads and egress
+/// are used. The storage statistics are enough to size the queries.
+async fn read_replica_workload_description(
+    diagnostics: &dyn Diagnostics,
+    model: &RequestUnitModel,
+    config: &WorkloadSourceConfiguration,
+    estimate_serverless: bool,
+) -> Result<WorkloadDescription> {
+    let pool = sqlx::MySqlPool::connect(&config.connection_string())
+        .await
+        .map_err(SourceError::Connection)?;
+    let provider = provider::detect(&pool).await?;
+    read_workload_description(
+        diagnostics,
+        model,
+        &pool,
+        config,
+        provider,
+        StorageOptions::default(),
+        estimate_serverless,
+    )
+    .await?
+    .ok_or_else(|| {
+        anyhow!(
+            "The replica {} runs on TiDB Serverless, which has no replicas to collect.",
+            config.name()
+        )
+    })
+}
+
+async fn read_workload_description(
+    diagnostics: &dyn Diagnostics,
+    model: &RequestUnitModel,
+    pool: &Pool<MySql>,
+    config: &WorkloadSourceConfiguration,
+    provider: Provider,
+    storage: StorageOptions,
+    estimate_serverless: bool,
+) -> Result<Option<WorkloadDescription>> {
+    let mut tables =
+        measure_tables_information(diagnostics, pool, &config.database, provider, storage.mode)
+            .await?;
+    if provider.is_tidb() {
+        if provider == Provider::TiDBServerless {
+            if !estimate_serverless {
+                return Ok(None);
+            }
+            Ok(Some(WorkloadDescription::serverless(
+                diagnostics,
+                model,
+                tables,
+                read_serverless_statements_summary(pool, &config.database).await?,
+            )))
         } else {
             Ok(Some(WorkloadDescription::tidb(
-                output,
+                diagnostics,
                 tables,
-                read_tidb_statements_summary(&pool, &config.database).await?,
-                read_tidb_system_metrics(&pool).await?,
+                read_tidb_statements_summary(pool, &config.database).await?,
+                read_tidb_system_metrics(pool).await?,
             )))
         }
-    } else if is_mysql_performance_schema_enabled(&pool).await? {
+    } else if is_mysql_performance_schema_enabled(pool).await? {
+        check_mysql_collection(diagnostics, pool, provider).await?;
+        if storage.convert {
+            tables.conversion = Some(encoding::encoded_size(
+                &read_table_schemas(pool, &config.database).await?,
+            ));
+        }
         Ok(Some(WorkloadDescription::mysql(
-            output,
+            diagnostics,
+            model,
             tables,
-            read_mysql_statements_summary(&pool, &config.database).await?,
+            read_mysql_statements_summary(
+                pool,
+                &config.database,
+                read_table_descriptions(pool, &config.database).await?,
+            )
+            .await?,
         )))
-    } else if is_mariadb(&pool).await? {
-        Err(anyhow!("Please enable the 'Performance Schema' on your MariaDB server and keep it active for at least a full business day to ensure comprehensive workload coverage. For instructions, see this guide: https://mariadb.com/kb/en/performance-schema-overview/#activating-the-performance-schema"))
     } else {
-        Err(anyhow!("Please enable the 'Performance Schema' on your MySQL server and keep it active for at least a full business day to ensure comprehensive workload coverage. For instructions, see this guide: https://dev.mysql.com/doc/refman/5.7/en/performance-schema-startup-configuration.html"))
+        Err(SourceError::Unsupported(provider.performance_schema_disabled()).into())
+    }
+}
+
+/// Warns when the source is a read-only replica, whose Performance Schema
+/// misses the writes and the reads served by the other servers.
+async fn check_primary(
+    diagnostics: &dyn Diagnostics,
+    pool: &Pool<MySql>,
+    provider: Provider,
+) -> Result<()> {
+    if provider::is_replica(pool, provider).await? {
+        if provider == Provider::AuroraMySQL {
+            diagnostics.warn("replica_source", "The source is an Aurora reader instance, whose Performance Schema only records the statements it served: the writes and the reads served by the other instances are missing. Connect to the cluster endpoint to collect the writer and list the readers as its replicas instead.");
+        } else {
+            diagnostics.warn("replica_source", &format!("The source is a read-only {} replica, whose Performance Schema only records the statements it served: the writes and the reads served by the other servers are missing. Connect to the primary to collect the writes and list the replicas under it instead.", provider));
+        }
     }
+    Ok(())
 }
 
+/// Warns about the settings of a MySQL source that leave the statements out
+/// of the Performance Schema.
+async fn check_mysql_collection(
+    diagnostics: &dyn Diagnostics,
+    pool: &Pool<MySql>,
+    provider: Provider,
+) -> Result<()> {
+    let consumer: Option<(String,)> = sqlx::query_as(
+        "SELECT ENABLED FROM performance_schema.setup_consumers WHERE NAME='statements_digest'",
+    )
+    .fetch_optional(pool)
+    .await?;
+    if consumer.is_some_and(|(enabled,)| enabled != "YES") {
+        diagnostics.warn("statements_digest_disabled", &format!("The 'statements_digest' consumer of the Performance Schema is disabled on your {} server, so no statement is summarized. Enable it with UPDATE performance_schema.setup_consumers SET ENABLED='YES' WHERE NAME='statements_digest' and keep it active for at least a full business day.", provider));
+    }
+    Ok(())
+}
+
+/// Failures of a workload source, told apart by the exit code.
+#[derive(Debug)]
+pub enum SourceError {
+    Connection(sqlx::Error),
+    Unsupported(String),
+}
+
+impl Display for SourceError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SourceError::Connection(e) => write!(f, "The connection failed: {}", e),
+            SourceError::Unsupported(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for SourceError {}
+
 #[derive(Debug, FromRow)]
 struct TablesInformation {
     total_rows: Option<u64>,
     total_data_in_bytes: Option<u64>,
     total_index_in_bytes: Option<u64>,
+    #[sqlx(skip)]
+    measurement: StorageMeasurement,
+    #[sqlx(skip)]
+    conversion: Option<EncodedSize>,
 }
 
 async fn check_variable_value(pool: &Pool<MySql>, variable: &str, value: &str) -> Result<bool> {
@@ -302,6 +1723,171 @@ async fn read_tables_information(pool: &Pool<MySql>, database: &str) -> Result<T
         .bind(database).fetch_one(pool).await?)
 }
 
+/// Reads the total size of the InnoDB tablespaces of the database, from the
+/// view of MySQL 8.0 or 5.7.
+async fn read_tablespaces_size(pool: &Pool<MySql>, database: &str) -> Option<u64> {
+    for view in ["INNODB_TABLESPACES", "INNODB_SYS_TABLESPACES"] {
+        let size: Option<(Option<u64>,)> = sqlx::query_as(&format!("SELECT CAST(SUM(FILE_SIZE) AS UNSIGNED) FROM information_schema.{} WHERE SUBSTRING_INDEX(NAME, '/', 1)=?", view))
+            .bind(database)
+            .fetch_optional(pool)
+            .await
+            .ok()
+            .flatten();
+        if let Some((Some(size),)) = size.filter(|(size,)| size.unwrap_or(0) > 0) {
+            return Some(size);
+        }
+    }
+    None
+}
+
+/// Reads the size of the clustered and of the secondary indexes of the
+/// database from the page counts of the persistent InnoDB statistics.
+async fn read_index_statistics_size(pool: &Pool<MySql>, database: &str) -> Option<(u64, u64)> {
+    let size: (Option<u64>, Option<u64>) = sqlx::query_as("SELECT CAST(SUM(IF(index_name IN ('PRIMARY', 'GEN_CLUST_INDEX'), stat_value, 0)) * @@innodb_page_size AS UNSIGNED), CAST(SUM(IF(index_name IN ('PRIMARY', 'GEN_CLUST_INDEX'), 0, stat_value)) * @@innodb_page_size AS UNSIGNED) FROM mysql.innodb_index_stats WHERE database_name=? AND stat_name='size'")
+        .bind(database)
+        .fetch_one(pool)
+        .await
+        .ok()?;
+    match size {
+        (Some(data_in_bytes), Some(index_in_bytes)) if data_in_bytes > 0 => {
+            Some((data_in_bytes, index_in_bytes))
+        }
+        _ => None,
+    }
+}
+
+/// Quotes an identifier with backticks, doubling those inside the name.
+fn quote_identifier(name: &str) -> String {
+    format!("`{}`", name.replace('`', "``"))
+}
+
+/// Counts the rows of every table up to the limit. A table reaching the
+/// limit keeps its statistics when they report more rows.
+async fn count_rows(pool: &Pool<MySql>, database: &str, limit: u64) -> Result<u64> {
+    let tables: Vec<(String, u64)> = sqlx::query_as("SELECT TABLE_NAME, CAST(IFNULL(TABLE_ROWS, 0) AS UNSIGNED) FROM information_schema.TABLES WHERE TABLE_SCHEMA=? AND TABLE_TYPE='BASE TABLE'")
+        .bind(database)
+        .fetch_all(pool)
+        .await?;
+    let mut total_rows = 0;
+    for (table, estimated_rows) in tables {
+        let (counted_rows,): (i64,) = sqlx::query_as(&format!(
+            "SELECT COUNT(*) FROM (SELECT 1 FROM {} LIMIT {}) AS sampled",
+            quote_identifier(&table),
+            limit
+        ))
+        .fetch_one(pool)
+        .await?;
+        let counted_rows = counted_rows as u64;
+        total_rows += if counted_rows >= limit {
+            max(counted_rows, estimated_rows)
+        } else {
+            counted_rows
+        };
+    }
+    Ok(total_rows)
+}
+
+/// Measures the storage of the database in the given mode. The exact mode
+/// prefers the tablespace file sizes, split between data and indexes as the
+/// statistics are, then the index page counts, then the statistics.
+async fn measure_tables_information(
+    diagnostics: &dyn Diagnostics,
+    pool: &Pool<MySql>,
+    database: &str,
+    provider: Provider,
+    mode: StorageMode,
+) -> Result<TablesInformation> {
+    let statistics = read_tables_information(pool, database).await?;
+    let StorageMode::Exact { row_count_limit } = mode else {
+        return Ok(statistics);
+    };
+    let total_rows = Some(count_rows(pool, database, row_count_limit).await?);
+    let tablespaces_size = if provider.has_tablespace_files() {
+        read_tablespaces_size(pool, database).await
+    } else {
+        None
+    };
+    if let Some(size) = tablespaces_size {
+        let data_in_bytes = statistics.total_data_in_bytes.unwrap_or(0);
+        let index_in_bytes = statistics.total_index_in_bytes.unwrap_or(0);
+        let data_ratio = if data_in_bytes + index_in_bytes > 0 {
+            data_in_bytes as f64 / (data_in_bytes + index_in_bytes) as f64
+        } else {
+            1f64
+        };
+        let data_in_bytes = (size as f64 * data_ratio) as u64;
+        return Ok(TablesInformation {
+            total_rows,
+            total_data_in_bytes: Some(data_in_bytes),
+            total_index_in_bytes: Some(size - data_in_bytes),
+            measurement: StorageMeasurement::Tablespaces,
+            conversion: None,
+        });
+    }
+    if let Some((data_in_bytes, index_in_bytes)) = read_index_statistics_size(pool, database).await
+    {
+        return Ok(TablesInformation {
+            total_rows,
+            total_data_in_bytes: Some(data_in_bytes),
+            total_index_in_bytes: Some(index_in_bytes),
+            measurement: StorageMeasurement::IndexStatistics,
+            conversion: None,
+        });
+    }
+    diagnostics.warn("storage_measurement_unavailable", "Neither the InnoDB tablespaces nor the index statistics are available, the storage is estimated from the table statistics instead.");
+    Ok(TablesInformation {
+        total_rows,
+        ..statistics
+    })
+}
+
+/// Reads the column and index definitions of every table along with its statistics.
+async fn read_table_schemas(pool: &Pool<MySql>, database: &str) -> Result<Vec<TableSchema>> {
+    let columns: Vec<ColumnDefinition> = sqlx::query_as("SELECT TABLE_NAME AS table_name, COLUMN_NAME AS name, CAST(DATA_TYPE AS CHAR) AS data_type, CAST(NUMERIC_PRECISION AS UNSIGNED) AS numeric_precision FROM information_schema.COLUMNS WHERE TABLE_SCHEMA=? ORDER BY TABLE_NAME, ORDINAL_POSITION")
+        .bind(database)
+        .fetch_all(pool)
+        .await?;
+    let indexes: Vec<IndexColumn> = sqlx::query_as("SELECT TABLE_NAME AS table_name, INDEX_NAME AS index_name, CAST(NON_UNIQUE AS SIGNED) AS non_unique, COLUMN_NAME AS column_name, CAST(SUB_PART AS UNSIGNED) AS sub_part FROM information_schema.STATISTICS WHERE TABLE_SCHEMA=? AND COLUMN_NAME IS NOT NULL ORDER BY TABLE_NAME, INDEX_NAME, SEQ_IN_INDEX")
+        .bind(database)
+        .fetch_all(pool)
+        .await?;
+    let mut tables: HashMap<String, TableSchema> = read_table_descriptions(pool, database)
+        .await?
+        .into_iter()
+        .map(|table| {
+            (
+                table.name.clone(),
+                TableSchema {
+                    name: table.name,
+                    rows: table.rows,
+                    data_in_bytes: table.data_in_bytes,
+                    index_in_bytes: table.index_in_bytes,
+                    ..Default::default()
+                },
+            )
+        })
+        .collect();
+    for column in columns {
+        if let Some(table) = tables.get_mut(&column.table_name) {
+            table.columns.push(column);
+        }
+    }
+    for index in indexes {
+        if let Some(table) = tables.get_mut(&index.table_name) {
+            table.indexes.push(index);
+        }
+    }
+    Ok(tables.into_values().collect())
+}
+
+async fn read_table_descriptions(
+    pool: &Pool<MySql>,
+    database: &str,
+) -> Result<Vec<TableDescription>> {
+    Ok(sqlx::query_as("SELECT TABLE_NAME AS name, CAST(IFNULL(TABLE_ROWS, 0) AS UNSIGNED) AS `rows`, CAST(IFNULL(DATA_LENGTH, 0) AS UNSIGNED) AS data_in_bytes, CAST(IFNULL(INDEX_LENGTH, 0) AS UNSIGNED) AS index_in_bytes FROM information_schema.TABLES WHERE TABLE_SCHEMA=? AND TABLE_TYPE='BASE TABLE' ORDER BY IFNULL(DATA_LENGTH, 0) + IFNULL(INDEX_LENGTH, 0) DESC")
+        .bind(database).fetch_all(pool).await?)
+}
+
 #[derive(Default, Debug)]
 struct MySQLStatementsSummary {
     read_queries: u64,
@@ -311,6 +1897,84 @@ struct MySQLStatementsSummary {
     write_rows: u64,
     start_time: DateTime<Utc>,
     end_time: DateTime<Utc>,
+    /// The tables of the database, referenced by the digests.
+    tables: Vec<TableDescription>,
+    digests: Vec<MySQLDigestTables>,
+}
+
+/// The rows examined by the reads, or affected by the writes, of a digest
+/// along with the tables its text references.
+#[derive(Debug)]
+struct MySQLDigestTables {
+    is_write: bool,
+    executions: u64,
+    rows: u64,
+    tables: Vec<usize>,
+}
+
+impl MySQLStatementsSummary {
+    /// The regions touched by an execution of a digest, whose rows are read
+    /// evenly from the tables it references: at least one region of every
+    /// table and at most all of them. A digest referencing no known table
+    /// reads from the whole schema as if it were uniform.
+    fn regions_per_execution(
+        &self,
+        digest: &MySQLDigestTables,
+        assumptions: &Assumptions,
+        region_size_in_bytes: u64,
+        average_row_size_in_bytes: u64,
+    ) -> u64 {
+        let rows_per_execution = digest.rows / max(digest.executions, 1);
+        if digest.tables.is_empty() {
+            return max(
+                rows_per_execution * average_row_size_in_bytes / region_size_in_bytes,
+                1,
+            );
+        }
+        let rows_per_table = rows_per_execution.div_ceil(digest.tables.len() as u64);
+        digest
+            .tables
+            .iter()
+            .map(|&table| {
+                let table = &self.tables[table];
+                let size_in_bytes = table.data_in_bytes + table.index_in_bytes;
+                let row_size_in_bytes = assumptions.row_size(size_in_bytes / max(table.rows, 1));
+                let table_regions = max(size_in_bytes.div_ceil(region_size_in_bytes), 1);
+                (rows_per_table * row_size_in_bytes)
+                    .div_ceil(region_size_in_bytes)
+                    .clamp(1, table_regions)
+            })
+            .sum()
+    }
+
+    /// The regions touched by all executions of the reads or the writes, none
+    /// when the digests are unknown.
+    fn regions(
+        &self,
+        is_write: bool,
+        assumptions: &Assumptions,
+        region_size_in_bytes: u64,
+        average_row_size_in_bytes: u64,
+    ) -> Option<u64> {
+        if self.digests.is_empty() {
+            return None;
+        }
+        Some(
+            self.digests
+                .iter()
+                .filter(|digest| digest.is_write == is_write)
+                .map(|digest| {
+                    digest.executions
+                        * self.regions_per_execution(
+                            digest,
+                            assumptions,
+                            region_size_in_bytes,
+                            average_row_size_in_bytes,
+                        )
+                })
+                .sum(),
+        )
+    }
 }
 
 #[derive(FromRow, Debug)]
@@ -336,9 +2000,59 @@ async fn is_mysql_performance_schema_enabled(pool: &Pool<MySql>) -> Result<bool>
     check_variable_value(pool, "performance_schema", "ON").await
 }
 
+/// Finds the tables a digest reads or writes. Only the comma-separated table
+/// lists following FROM, JOIN, UPDATE or INTO count, so a column named like a
+/// table is not mistaken for it.
+struct TableReferences {
+    lists: Regex,
+    table: Regex,
+}
+
+impl TableReferences {
+    fn new() -> Result<Self> {
+        Ok(Self {
+            lists: Regex::new(&format!(
+                "(?i)\\b(?:FROM|JOIN|UPDATE|INTO)\\s+({0}(?:\\s*,\\s*{0})*)",
+                TABLE_REFERENCE
+            ))?,
+            table: Regex::new(TABLE_REFERENCE)?,
+        })
+    }
+
+    fn names<'a>(&'a self, sql: &'a str) -> impl Iterator<Item = &'a str> {
+        self.lists
+            .captures_iter(sql)
+            .filter_map(|captures| captures.get(1))
+            .flat_map(|list| self.table.captures_iter(list.as_str()))
+            .filter_map(|captures| captures.get(1))
+            .map(|name| name.as_str())
+    }
+}
+
+/// The tables of the database a digest references, in order of appearance.
+fn referenced_tables(
+    references: &TableReferences,
+    sql: &str,
+    tables: &[TableDescription],
+) -> Vec<usize> {
+    let mut referenced = Vec::new();
+    for name in references.names(sql) {
+        if let Some(table) = tables
+            .iter()
+            .position(|table| table.name.eq_ignore_ascii_case(name))
+        {
+            if !referenced.contains(&table) {
+                referenced.push(table);
+            }
+        }
+    }
+    referenced
+}
+
 async fn read_mysql_statements_summary(
     pool: &Pool<MySql>,
     database: &str,
+    tables: Vec<TableDescription>,
 ) -> Result<MySQLStatementsSummary> {
     let statements_summary: Vec<MySQLStatementSummary> =
         sqlx::query_as("SELECT DIGEST_TEXT, COUNT_STAR, SUM_ROWS_AFFECTED, SUM_ROWS_SENT, SUM_ROWS_EXAMINED, FIRST_SEEN, LAST_SEEN FROM performance_schema.events_statements_summary_by_digest WHERE SCHEMA_NAME=? AND LAST_SEEN >= DATE_SUB(NOW(), INTERVAL 7 DAY)")
@@ -349,14 +2063,17 @@ async fn read_mysql_statements_summary(
         return Ok(MySQLStatementsSummary {
             end_time: now,
             start_time: seven_days_ago,
+            tables,
             ..Default::default()
         });
     }
     let is_write_pattern = Regex::new("^INSERT |^DELETE |^UPDATE ")?;
+    let references = TableReferences::new()?;
     Ok(statements_summary.into_iter().fold(
         MySQLStatementsSummary {
             start_time: now,
             end_time: seven_days_ago,
+            tables,
             ..Default::default()
         },
         |mut acc, statement| -> MySQLStatementsSummary {
@@ -365,11 +2082,22 @@ async fn read_mysql_statements_summary(
             acc.read_rows += statement.read_rows;
             acc.sent_rows += statement.sent_rows;
             acc.write_rows += statement.affected_rows;
-            if is_write_pattern.find(&statement.sql).is_some() {
+            let is_write = is_write_pattern.find(&statement.sql).is_some();
+            if is_write {
                 acc.write_queries += statement.count;
             } else {
                 acc.read_queries += statement.count;
             }
+            acc.digests.push(MySQLDigestTables {
+                is_write,
+                executions: statement.count,
+                rows: if is_write {
+                    statement.affected_rows
+                } else {
+                    statement.read_rows
+                },
+                tables: referenced_tables(&references, &statement.sql, &acc.tables),
+            });
             acc
         },
     ))
@@ -386,7 +2114,7 @@ struct TiDBStatementsSummary {
     end_time: DateTime<Utc>,
 }
 
-#[derive(FromRow, Debug)]
+#[derive(FromRow, Debug, Clone)]
 struct TiDBStatementSummary {
     #[sqlx(rename = "STMT_TYPE")]
     statement_type: String,
@@ -404,6 +2132,81 @@ struct TiDBStatementSummary {
     last_seen: DateTime<Utc>,
 }
 
+impl TiDBStatementSummary {
+    fn is_write(&self) -> bool {
+        matches!(
+            self.statement_type.as_str(),
+            "Delete" | "Update" | "Insert" | "Replace"
+        )
+    }
+}
+
+impl TiDBStatementsSummary {
+    fn add(&mut self, statement: &TiDBStatementSummary) {
+        self.end_time = max(statement.last_seen, self.end_time);
+        self.start_time = min(statement.first_seen, self.start_time);
+        self.read_rows += statement.avg_processed_keys * statement.count;
+        self.sent_rows += statement.avg_result_rows * statement.count;
+        self.write_bytes += statement.avg_write_bytes * statement.count;
+        if statement.is_write() {
+            self.write_queries += statement.count;
+        } else {
+            self.read_queries += statement.count;
+        }
+    }
+}
+
+#[derive(FromRow, Debug)]
+struct ServerlessStatementSummary {
+    #[sqlx(rename = "DIGEST")]
+    digest: String,
+    #[sqlx(rename = "DIGEST_TEXT")]
+    sql: String,
+    #[sqlx(flatten)]
+    statement: TiDBStatementSummary,
+    #[sqlx(rename = "AVG_REQUEST_UNITS")]
+    avg_request_units: f64,
+}
+
+/// Statements of a digest merged across instances and summary windows, the
+/// averages are kept in the statement for the merged execution count.
+#[derive(Debug)]
+struct ServerlessDigestSummary {
+    digest: String,
+    sql: String,
+    statement: TiDBStatementSummary,
+    request_units: f64,
+}
+
+impl ServerlessDigestSummary {
+    fn add(&mut self, statement: &TiDBStatementSummary, request_units: f64) {
+        let count = self.statement.count + statement.count;
+        if count > 0 {
+            let average = |merged: u64, other: u64| {
+                (merged * self.statement.count + other * statement.count) / count
+            };
+            self.statement.avg_result_rows =
+                average(self.statement.avg_result_rows, statement.avg_result_rows);
+            self.statement.avg_processed_keys = average(
+                self.statement.avg_processed_keys,
+                statement.avg_processed_keys,
+            );
+            self.statement.avg_write_bytes =
+                average(self.statement.avg_write_bytes, statement.avg_write_bytes);
+        }
+        self.statement.count = count;
+        self.request_units += request_units;
+    }
+}
+
+#[derive(Debug, Default)]
+struct ServerlessStatementsSummary {
+    statements: TiDBStatementsSummary,
+    /// Total request units of all statements, `None` when they are not exposed.
+    request_units: Option<f64>,
+    digests: Vec<ServerlessDigestSummary>,
+}
+
 #[derive(Debug, Default)]
 struct TiDBSystemMetrics {
     write_bytes_per_hour: u64,
@@ -491,41 +2294,255 @@ async fn read_tidb_statements_summary(
             ..Default::default()
         },
         |mut acc, statement| -> TiDBStatementsSummary {
-            acc.end_time = max(statement.last_seen, acc.end_time);
-            acc.start_time = min(statement.first_seen, acc.start_time);
-            acc.read_rows += statement.avg_processed_keys * statement.count;
-            acc.sent_rows += statement.avg_result_rows * statement.count;
-            acc.write_bytes += statement.avg_write_bytes * statement.count;
-            if matches!(
-                statement.statement_type.as_str(),
-                "Delete" | "Update" | "Insert" | "Replace"
-            ) {
-                acc.write_queries += statement.count;
-            } else {
-                acc.read_queries += statement.count;
-            }
+            acc.add(&statement);
             acc
         },
     )))
 }
 
-async fn check_version_signature(pool: &Pool<MySql>, pattern: &str) -> Result<bool> {
-    let version: (String,) = sqlx::query_as("SELECT version()").fetch_one(pool).await?;
-    Ok(Regex::new(pattern)?.find(&version.0).is_some())
-}
+const SERVERLESS_REQUEST_UNIT_COLUMNS: [&str; 2] =
+    ["AVG_REQUEST_UNIT_READ", "AVG_REQUEST_UNIT_WRITE"];
 
-async fn is_tidb(pool: &Pool<MySql>) -> Result<bool> {
-    check_version_signature(pool, "^\\d+\\.\\d+\\.\\d+-(?i)TiDB(?-i)-.*").await
+async fn has_serverless_request_unit_columns(pool: &Pool<MySql>) -> Result<bool> {
+    let (count,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM information_schema.COLUMNS WHERE TABLE_SCHEMA='information_schema' AND TABLE_NAME='CLUSTER_STATEMENTS_SUMMARY' AND COLUMN_NAME IN (?, ?)")
+        .bind(SERVERLESS_REQUEST_UNIT_COLUMNS[0])
+        .bind(SERVERLESS_REQUEST_UNIT_COLUMNS[1])
+        .fetch_one(pool)
+        .await?;
+    Ok(count as usize == SERVERLESS_REQUEST_UNIT_COLUMNS.len())
 }
 
-async fn is_tidb_serverless(pool: &Pool<MySql>) -> Result<bool> {
-    check_version_signature(
-        pool,
-        "^\\d+\\.\\d+\\.\\d+-(?i)TiDB(?-i)-v\\d+\\.\\d+\\.\\d+-(?i)serverless(?-i).*",
-    )
-    .await
+async fn read_serverless_statements_summary(
+    pool: &Pool<MySql>,
+    database: &str,
+) -> Result<ServerlessStatementsSummary> {
+    let measured = has_serverless_request_unit_columns(pool).await?;
+    let request_units = if measured {
+        "AVG_REQUEST_UNIT_READ + AVG_REQUEST_UNIT_WRITE"
+    } else {
+        "0E0"
+    };
+    let columns = format!("DIGEST, DIGEST_TEXT, STMT_TYPE, EXEC_COUNT, CAST(AVG_RESULT_ROWS AS UNSIGNED) AS AVG_RESULT_ROWS, AVG_PROCESSED_KEYS, CAST(AVG_WRITE_SIZE AS UNSIGNED) AS AVG_WRITE_SIZE, {} AS AVG_REQUEST_UNITS, FIRST_SEEN, LAST_SEEN", request_units);
+    let statements_summary: Vec<ServerlessStatementSummary> = sqlx::query_as(&format!(
+        "SELECT {} FROM information_schema.CLUSTER_STATEMENTS_SUMMARY WHERE SCHEMA_NAME=? AND LAST_SEEN >= DATE_SUB(NOW(), INTERVAL 7 DAY) UNION ALL SELECT {} FROM information_schema.CLUSTER_STATEMENTS_SUMMARY_HISTORY WHERE SCHEMA_NAME=? AND LAST_SEEN >= DATE_SUB(NOW(), INTERVAL 7 DAY)",
+        columns, columns
+    ))
+    .bind(database)
+    .bind(database)
+    .fetch_all(pool)
+    .await?;
+    let now = Utc::now();
+    let seven_days_ago = now.sub(Duration::days(7));
+    let mut summary = ServerlessStatementsSummary {
+        statements: TiDBStatementsSummary {
+            start_time: now,
+            end_time: seven_days_ago,
+            ..Default::default()
+        },
+        request_units: measured.then_some(0f64),
+        ..Default::default()
+    };
+    if statements_summary.is_empty() {
+        summary.statements.start_time = seven_days_ago;
+        summary.statements.end_time = now;
+        return Ok(summary);
+    }
+    let mut digests: HashMap<String, ServerlessDigestSummary> = HashMap::new();
+    for statement in statements_summary {
+        let request_units = statement.avg_request_units * statement.statement.count as f64;
+        summary.statements.add(&statement.statement);
+        if let Some(total) = summary.request_units.as_mut() {
+            *total += request_units;
+        }
+        let digest =
+            digests
+                .entry(statement.digest.clone())
+                .or_insert_with(|| ServerlessDigestSummary {
+                    digest: statement.digest,
+                    sql: statement.sql,
+                    statement: TiDBStatementSummary {
+                        count: 0,
+                        avg_result_rows: 0,
+                        avg_processed_keys: 0,
+                        avg_write_bytes: 0,
+                        ..statement.statement.clone()
+                    },
+                    request_units: 0f64,
+                });
+        digest.add(&statement.statement, request_units);
+    }
+    summary.digests = digests.into_values().collect();
+    Ok(summary)
 }
 
-async fn is_mariadb(pool: &Pool<MySql>) -> Result<bool> {
-    check_version_signature(pool, "^\\d+\\.\\d+\\.\\d+-(?i)MariaDB(?-i)-.*").await
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tables(names: &[&str]) -> Vec<TableDescription> {
+        names
+            .iter()
+            .map(|name| TableDescription {
+                name: name.to_string(),
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn referenced_tables_skip_columns_named_like_tables() {
+        let references = TableReferences::new().unwrap();
+        let tables = tables(&["orders", "status", "users"]);
+        assert_eq!(
+            referenced_tables(
+                &references,
+                "SELECT `status` FROM `orders` JOIN `users` ON `orders` . `user_id` = `users` . `id` WHERE `status` = ?",
+                &tables
+            ),
+            vec![0, 2]
+        );
+        assert_eq!(
+            referenced_tables(
+                &references,
+                "UPDATE `orders` SET `status` = ? WHERE `id` = ?",
+                &tables
+            ),
+            vec![0]
+        );
+    }
+
+    #[test]
+    fn referenced_tables_qualified_and_inserted() {
+        let references = TableReferences::new().unwrap();
+        let tables = tables(&["orders", "status"]);
+        assert_eq!(
+            referenced_tables(
+                &references,
+                "INSERT INTO `shop` . `Status` ( `orders` ) SELECT `orders` FROM `shop` . `orders`",
+                &tables
+            ),
+            vec![1, 0]
+        );
+        assert!(referenced_tables(&references, "SELECT `orders` , `status`", &tables).is_empty());
+    }
+
+    #[test]
+    fn referenced_tables_comma_join() {
+        let references = TableReferences::new().unwrap();
+        let tables = tables(&["orders", "status", "users", "items"]);
+        assert_eq!(
+            referenced_tables(
+                &references,
+                "SELECT `status` FROM `orders` `o` , `shop` . `users` AS `u` , `items` WHERE `o` . `user_id` = `u` . `id`",
+                &tables
+            ),
+            vec![0, 2, 3]
+        );
+        assert_eq!(
+            referenced_tables(
+                &references,
+                "DELETE FROM `items` WHERE `id` IN ( SELECT `id` FROM `orders` , `users` )",
+                &tables
+            ),
+            vec![3, 0, 2]
+        );
+    }
+
+    #[test]
+    fn expand_host_pattern_pads_to_the_lower_bound() {
+        assert_eq!(
+            expand_host_pattern("shard-[01-03].db").unwrap(),
+            vec!["shard-01.db", "shard-02.db", "shard-03.db"]
+        );
+        assert_eq!(
+            expand_host_pattern("shard-[8-10]").unwrap(),
+            vec!["shard-8", "shard-9", "shard-10"]
+        );
+        assert_eq!(expand_host_pattern("primary").unwrap(), vec!["primary"]);
+    }
+
+    #[test]
+    fn expand_host_pattern_multiple_ranges() {
+        assert_eq!(
+            expand_host_pattern("dc[1-2]-shard[0-1]").unwrap(),
+            vec!["dc1-shard0", "dc1-shard1", "dc2-shard0", "dc2-shard1"]
+        );
+    }
+
+    #[test]
+    fn expand_host_pattern_rejects_reversed_and_large_ranges() {
+        assert!(expand_host_pattern("shard-[3-1]").is_err());
+        assert!(expand_host_pattern("shard-[1-2000]").is_err());
+        assert!(expand_host_pattern("a[1-64]b[1-64]").is_err());
+    }
+
+    #[test]
+    fn merge_shards_sums_digests_and_tables() {
+        fn shard(digests: &[(&str, u64)], tables: &[(&str, u64)]) -> WorkloadDescription {
+            WorkloadDescription {
+                digests: digests
+                    .iter()
+                    .map(|(digest, request_units)| DigestDescription {
+                        digest: digest.to_string(),
+                        executions_per_hour: 10,
+                        request_units_per_hour: *request_units,
+                        ..Default::default()
+                    })
+                    .collect(),
+                tables: tables
+                    .iter()
+                    .map(|(name, data)| TableDescription {
+                        name: name.to_string(),
+                        rows: 1,
+                        data_in_bytes: *data,
+                        index_in_bytes: 0,
+                    })
+                    .collect(),
+                ..Default::default()
+            }
+        }
+        let merged = WorkloadDescription::merge_shards(
+            &RequestUnitModel::default(),
+            vec![
+                (
+                    "shard-1".into(),
+                    shard(&[("a", 100), ("b", 50)], &[("orders", 300), ("users", 200)]),
+                ),
+                (
+                    "shard-2".into(),
+                    shard(&[("b", 80), ("c", 10)], &[("users", 200), ("items", 50)]),
+                ),
+            ],
+        );
+
+        let digests: Vec<_> = merged
+            .digests
+            .iter()
+            .map(|digest| {
+                (
+                    digest.digest.as_str(),
+                    digest.executions_per_hour,
+                    digest.request_units_per_hour,
+                )
+            })
+            .collect();
+        assert_eq!(digests, vec![("b", 20, 130), ("a", 10, 100), ("c", 10, 10)]);
+
+        let tables: Vec<_> = merged
+            .tables
+            .iter()
+            .map(|table| (table.name.as_str(), table.rows, table.data_in_bytes))
+            .collect();
+        assert_eq!(
+            tables,
+            vec![("users", 2, 400), ("orders", 1, 300), ("items", 1, 50)]
+        );
+
+        let shards: Vec<_> = merged
+            .shards
+            .iter()
+            .map(|shard| shard.name.as_str())
+            .collect();
+        assert_eq!(shards, vec!["shard-1", "shard-2"]);
+    }
 }
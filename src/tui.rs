@@ -0,0 +1,389 @@
+use anyhow::Result;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, List, ListItem, ListState, Paragraph, Row, Table};
+use ratatui::{DefaultTerminal, Frame};
+
+use crate::calculator::{self, WorkloadEstimation};
+use crate::model::RequestUnitModel;
+use crate::scenario::{Adjustment, Scenario};
+use crate::source::WorkloadDescription;
+
+const TRAFFIC_STEP: f64 = 0.1;
+const GROWTH_STEP: f64 = 10f64;
+const SPENDING_LIMIT_STEP: f64 = 10f64;
+const HOURS_PER_MONTH: f64 = 730f64;
+const MEGA: f64 = 1024f64 * 1024f64;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Breakdown {
+    Digests,
+    Tables,
+}
+
+/// The assumptions adjusted interactively and the estimations recomputed from them.
+struct App<'a> {
+    model: &'a RequestUnitModel,
+    workloads: Vec<WorkloadDescription>,
+    regions: Vec<&'static str>,
+    region: usize,
+    traffic_multiplier: f64,
+    storage_growth_percent: f64,
+    spending_limit: f64,
+    breakdown: Breakdown,
+    clusters: ListState,
+    estimations: Vec<WorkloadEstimation>,
+}
+
+impl<'a> App<'a> {
+    fn new(region: &str, model: &'a RequestUnitModel, workloads: Vec<WorkloadDescription>) -> Self {
+        let regions: Vec<&'static str> = calculator::regions().collect();
+        let mut app = Self {
+            model,
+            workloads,
+            region: regions.iter().position(|r| *r == region).unwrap_or(0),
+            regions,
+            traffic_multiplier: 1f64,
+            storage_growth_percent: 0f64,
+            spending_limit: 0f64,
+            breakdown: Breakdown::Digests,
+            clusters: ListState::default().with_selected(Some(0)),
+            estimations: Vec::new(),
+        };
+        app.recompute();
+        app
+    }
+
+    fn region(&self) -> &'static str {
+        self.regions[self.region]
+    }
+
+    fn scenario(&self) -> Scenario {
+        let traffic = Some(Adjustment::Multiplier(self.traffic_multiplier));
+        Scenario {
+            name: "interactive".into(),
+            read_requests_per_hour: traffic,
+            read_bytes_per_hour: traffic,
+            write_requests_per_hour: traffic,
+            write_bytes_per_hour: traffic,
+            egress_bytes_per_hour: traffic,
+            storage_in_bytes: Some(Adjustment::Multiplier(
+                1f64 + self.storage_growth_percent / 100f64,
+            )),
+            ..Default::default()
+        }
+    }
+
+    fn recompute(&mut self) {
+        let scenario = self.scenario();
+        let adjusted: Vec<WorkloadDescription> = self
+            .workloads
+            .iter()
+            .map(|workload| scenario.apply(self.model, workload))
+            .collect();
+        self.estimations =
+            calculator::estimate(self.region(), self.model, &adjusted).unwrap_or_default();
+    }
+
+    fn selected(&self) -> usize {
+        self.clusters
+            .selected()
+            .unwrap_or(0)
+            .min(self.workloads.len().saturating_sub(1))
+    }
+
+    /// Handles a key press, returns false when the user quits.
+    fn handle(&mut self, key: KeyCode) -> bool {
+        match key {
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            KeyCode::Up | KeyCode::Char('k') => self.clusters.select_previous(),
+            KeyCode::Down | KeyCode::Char('j') => {
+                if self.selected() + 1 < self.workloads.len() {
+                    self.clusters.select_next();
+                }
+            }
+            KeyCode::Tab => {
+                self.breakdown = match self.breakdown {
+                    Breakdown::Digests => Breakdown::Tables,
+                    Breakdown::Tables => Breakdown::Digests,
+                }
+            }
+            KeyCode::Char('r') => self.region = (self.region + 1) % self.regions.len(),
+            KeyCode::Char('R') => {
+                self.region = (self.region + self.regions.len() - 1) % self.regions.len()
+            }
+            KeyCode::Char('t') => self.traffic_multiplier += TRAFFIC_STEP,
+            KeyCode::Char('T') => {
+                self.traffic_multiplier = (self.traffic_multiplier - TRAFFIC_STEP).max(0f64)
+            }
+            KeyCode::Char('g') => self.storage_growth_percent += GROWTH_STEP,
+            KeyCode::Char('G') => {
+                self.storage_growth_percent =
+                    (self.storage_growth_percent - GROWTH_STEP).max(-100f64)
+            }
+            KeyCode::Char('l') => self.spending_limit += SPENDING_LIMIT_STEP,
+            KeyCode::Char('L') => {
+                self.spending_limit = (self.spending_limit - SPENDING_LIMIT_STEP).max(0f64)
+            }
+            _ => return true,
+        }
+        self.recompute();
+        true
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [header, body, footer] = Layout::vertical([
+            Constraint::Length(3),
+            Constraint::Min(0),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+        let [clusters, details] =
+            Layout::horizontal([Constraint::Percentage(30), Constraint::Percentage(70)])
+                .areas(body);
+        let [costs, breakdown] =
+            Layout::vertical([Constraint::Length(10), Constraint::Min(0)]).areas(details);
+
+        frame.render_widget(
+            Paragraph::new(Line::from(vec![
+                "Region ".into(),
+                self.region().bold().green(),
+                "   Traffic ".into(),
+                format!("x{:.1}", self.traffic_multiplier).bold().green(),
+                "   Storage growth ".into(),
+                format!("{:+.0}%", self.storage_growth_percent)
+                    .bold()
+                    .green(),
+                "   Spending limit ".into(),
+                if self.spending_limit > 0f64 {
+                    format!("${:.2}", self.spending_limit)
+                } else {
+                    "none".into()
+                }
+                .bold()
+                .green(),
+            ]))
+            .block(Block::bordered().title(" TiDB Serverless Cost Estimation ")),
+            header,
+        );
+        self.draw_clusters(frame, clusters);
+        self.draw_costs(frame, costs);
+        match self.breakdown {
+            Breakdown::Digests => self.draw_digests(frame, breakdown),
+            Breakdown::Tables => self.draw_tables(frame, breakdown),
+        }
+        frame.render_widget(
+            Paragraph::new(
+                "↑/↓ cluster  r/R region  t/T traffic  g/G growth  l/L spending limit  Tab breakdown  q quit",
+            )
+            .style(Style::default().fg(Color::DarkGray)),
+            footer,
+        );
+    }
+
+    fn draw_clusters(&mut self, frame: &mut Frame, area: Rect) {
+        let items: Vec<ListItem> = self
+            .workloads
+            .iter()
+            .zip(&self.estimations)
+            .map(|(workload, estimation)| {
+                ListItem::new(format!(
+                    "{}  ${:.2}",
+                    workload.name,
+                    estimation.total_cost()
+                ))
+            })
+            .collect();
+        frame.render_stateful_widget(
+            List::new(items)
+                .block(Block::bordered().title(" Clusters "))
+                .highlight_style(Style::default().add_modifier(Modifier::REVERSED)),
+            area,
+            &mut self.clusters,
+        );
+    }
+
+    fn draw_costs(&self, frame: &mut Frame, area: Rect) {
+        let Some(estimation) = self.estimations.get(self.selected()) else {
+            return;
+        };
+        let total = estimation.total_cost_range();
+        let money = |cost: f64| {
+            if cost < 0f64 {
+                format!("-${:.2}", -cost)
+            } else {
+                format!("${:.2}", cost)
+            }
+        };
+        let mut rows = vec![
+            Row::new(vec![
+                "Request Units".to_string(),
+                money(estimation.request_units_cost_range.low),
+                money(estimation.request_units_cost),
+                money(estimation.request_units_cost_range.high),
+            ]),
+            Row::new(vec![
+                "Row-based Storage".to_string(),
+                money(estimation.storage_cost_range.low),
+                money(estimation.storage_cost),
+                money(estimation.storage_cost_range.high),
+            ]),
+            Row::new(vec![
+                "Free Credits".to_string(),
+                money(-estimation.free_credit),
+                money(-estimation.free_credit),
+                money(-estimation.free_credit),
+            ]),
+            Row::new(vec![
+                "Total".to_string(),
+                money(total.low),
+                money(estimation.total_cost()),
+                money(total.high),
+            ])
+            .bold(),
+        ];
+        if self.spending_limit > 0f64 {
+            let over = estimation.total_cost() - self.spending_limit;
+            rows.push(if over > 0f64 {
+                Row::new(vec![
+                    "Spending limit".to_string(),
+                    String::new(),
+                    format!("exceeded by ${:.2}, throttled", over),
+                ])
+                .red()
+            } else {
+                Row::new(vec![
+                    "Spending limit".to_string(),
+                    String::new(),
+                    format!("${:.2} left", -over),
+                ])
+                .green()
+            });
+        }
+        frame.render_widget(
+            Table::new(
+                rows,
+                [
+                    Constraint::Length(20),
+                    Constraint::Length(12),
+                    Constraint::Min(12),
+                    Constraint::Length(12),
+                ],
+            )
+            .header(Row::new(vec!["SKU", "Low", "Expected", "High"]).bold())
+            .block(Block::bordered().title(" Monthly cost ")),
+            area,
+        );
+    }
+
+    fn draw_digests(&self, frame: &mut Frame, area: Rect) {
+        let index = self.selected();
+        let (Some(workload), Some(estimation)) =
+            (self.workloads.get(index), self.estimations.get(index))
+        else {
+            return;
+        };
+        let request_units_per_hour =
+            (estimation.usage.request_units_in_million as f64 * MEGA / HOURS_PER_MONTH).max(1f64);
+        let rows: Vec<Row> = workload
+            .digests
+            .iter()
+            .map(|digest| {
+                let request_units = digest.request_units_per_hour as f64 * self.traffic_multiplier;
+                let share = (request_units / request_units_per_hour).min(1f64);
+                Row::new(vec![
+                    digest.sql.clone(),
+                    format!("{:.0}", request_units),
+                    format!("{:.1}%", share * 100f64),
+                    format!("${:.2}", share * estimation.request_units_cost),
+                ])
+            })
+            .collect();
+        frame.render_widget(
+            Table::new(
+                rows,
+                [
+                    Constraint::Min(20),
+                    Constraint::Length(12),
+                    Constraint::Length(8),
+                    Constraint::Length(12),
+                ],
+            )
+            .header(Row::new(vec!["Statement", "RU/h", "Share", "Monthly"]).bold())
+            .block(Block::bordered().title(" Statements by request units (Tab: tables) ")),
+            area,
+        );
+    }
+
+    fn draw_tables(&self, frame: &mut Frame, area: Rect) {
+        let index = self.selected();
+        let (Some(workload), Some(estimation)) =
+            (self.workloads.get(index), self.estimations.get(index))
+        else {
+            return;
+        };
+        let total: u64 = workload
+            .tables
+            .iter()
+            .map(|table| table.data_in_bytes + table.index_in_bytes)
+            .sum();
+        let growth = 1f64 + self.storage_growth_percent / 100f64;
+        let rows: Vec<Row> = workload
+            .tables
+            .iter()
+            .map(|table| {
+                let storage = table.data_in_bytes + table.index_in_bytes;
+                let share = storage as f64 / total.max(1) as f64;
+                Row::new(vec![
+                    table.name.clone(),
+                    format!("{}", table.rows),
+                    format!("{:.1}", storage as f64 * growth / MEGA),
+                    format!("{:.1}%", share * 100f64),
+                    format!("${:.2}", share * estimation.storage_cost),
+                ])
+            })
+            .collect();
+        frame.render_widget(
+            Table::new(
+                rows,
+                [
+                    Constraint::Min(20),
+                    Constraint::Length(12),
+                    Constraint::Length(14),
+                    Constraint::Length(8),
+                    Constraint::Length(12),
+                ],
+            )
+            .header(Row::new(vec!["Table", "Rows", "Storage (MiB)", "Share", "Monthly"]).bold())
+            .block(Block::bordered().title(" Tables by storage (Tab: statements) ")),
+            area,
+        );
+    }
+}
+
+fn run_app(terminal: &mut DefaultTerminal, mut app: App) -> Result<()> {
+    loop {
+        terminal.draw(|frame| app.draw(frame))?;
+        if let Event::Key(key) = event::read()? {
+            if key.kind == KeyEventKind::Press && !app.handle(key.code) {
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Explores the estimations in an interactive terminal UI, recomputing them
+/// whenever the region, traffic, storage growth or spending limit changes.
+pub fn run(
+    region: &str,
+    model: &RequestUnitModel,
+    workloads: Vec<WorkloadDescription>,
+) -> Result<()> {
+    let app = App::new(region, model, workloads);
+    let mut terminal = ratatui::init();
+    let result = run_app(&mut terminal, app);
+    ratatui::restore();
+    result
+}